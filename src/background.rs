@@ -0,0 +1,157 @@
+use std::fmt;
+
+use iced::{Color, Vector};
+use iced_native::widget::Tree;
+use iced_native::{
+    image, layout, renderer, Element, Layout, Length, Point, Rectangle, Size, Widget,
+};
+use serde::{Deserialize, Serialize};
+
+/// Extensions offered by the file picker, all of them decoded by iced's image support.
+pub const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+/// Smaller images are scaled up to tile, so a tiny pattern doesn't take thousands of draws.
+const MIN_TILE_SIZE: f32 = 64.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BackgroundFit {
+    /// Fills the area, cropping whatever sticks out.
+    #[default]
+    Cover,
+    /// Shows the whole image, leaving bars of the chroma color.
+    Contain,
+    Stretch,
+    /// Repeats the image at its own size, or at least [`MIN_TILE_SIZE`] pixels.
+    Tile,
+}
+
+impl BackgroundFit {
+    pub const ALL: [BackgroundFit; 4] = [
+        BackgroundFit::Cover,
+        BackgroundFit::Contain,
+        BackgroundFit::Stretch,
+        BackgroundFit::Tile,
+    ];
+}
+
+impl fmt::Display for BackgroundFit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BackgroundFit::Cover => "Cover",
+            BackgroundFit::Contain => "Contain",
+            BackgroundFit::Stretch => "Stretch",
+            BackgroundFit::Tile => "Tile",
+        })
+    }
+}
+
+/// An image filling the whole area, meant as the base of a [`crate::stack::Stack`].
+pub struct Background<Handle> {
+    pub handle: Handle,
+    pub fit: BackgroundFit,
+    pub opacity: f32,
+    /// Color the image fades into as the opacity drops, i.e. whatever is behind it.
+    pub backdrop: Color,
+}
+
+impl<Message, Renderer> Widget<Message, Renderer> for Background<Renderer::Handle>
+where
+    Renderer: image::Renderer,
+{
+    fn width(&self) -> Length {
+        Length::Fill
+    }
+
+    fn height(&self) -> Length {
+        Length::Fill
+    }
+
+    fn layout(&self, _renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        layout::Node::new(limits.max())
+    }
+
+    fn draw(
+        &self,
+        _state: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Renderer::Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor_position: Point,
+        _viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let dimensions = renderer.dimensions(&self.handle);
+
+        if dimensions.width == 0 || dimensions.height == 0 {
+            return;
+        }
+
+        let image = Size::new(dimensions.width as f32, dimensions.height as f32);
+
+        // The layer clips whatever is cropped by the cover and tile fits.
+        renderer.with_layer(bounds, |renderer| match self.fit {
+            BackgroundFit::Stretch => renderer.draw(self.handle.clone(), bounds),
+            BackgroundFit::Cover | BackgroundFit::Contain => {
+                let (x, y) = (bounds.width / image.width, bounds.height / image.height);
+                let scale = if self.fit == BackgroundFit::Cover {
+                    x.max(y)
+                } else {
+                    x.min(y)
+                };
+                let size = Size::new(image.width * scale, image.height * scale);
+
+                renderer.draw(
+                    self.handle.clone(),
+                    Rectangle::new(
+                        bounds.center() - Vector::new(size.width / 2., size.height / 2.),
+                        size,
+                    ),
+                );
+            }
+            BackgroundFit::Tile => {
+                let scale = (MIN_TILE_SIZE / image.width.min(image.height)).max(1.);
+                let tile = Size::new(image.width * scale, image.height * scale);
+                let mut y = bounds.y;
+
+                while y < bounds.y + bounds.height {
+                    let mut x = bounds.x;
+
+                    while x < bounds.x + bounds.width {
+                        renderer.draw(self.handle.clone(), Rectangle::new(Point::new(x, y), tile));
+                        x += tile.width;
+                    }
+
+                    y += tile.height;
+                }
+            }
+        });
+
+        // Images have no opacity of their own, so fade them by covering them with the backdrop.
+        // This needs a layer of its own, images are drawn after quads within one.
+        if self.opacity < 1. {
+            renderer.with_layer(bounds, |renderer| {
+                renderer.fill_quad(
+                    renderer::Quad {
+                        bounds,
+                        border_radius: 0.0.into(),
+                        border_width: 0.,
+                        border_color: Color::TRANSPARENT,
+                    },
+                    Color {
+                        a: 1. - self.opacity.max(0.),
+                        ..self.backdrop
+                    },
+                );
+            });
+        }
+    }
+}
+
+impl<'a, Message, Renderer> From<Background<Renderer::Handle>> for Element<'a, Message, Renderer>
+where
+    Renderer: 'a + image::Renderer,
+{
+    fn from(background: Background<Renderer::Handle>) -> Self {
+        Element::new(background)
+    }
+}
@@ -0,0 +1,350 @@
+use std::path::PathBuf;
+
+use clap::builder::PossibleValue;
+use clap::{value_parser, Arg, ArgAction, Command, ValueEnum};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::diagnostics;
+use crate::source::{self, CaptureError, DeviceInfo, DeviceSelection};
+use crate::VisualMode;
+
+/// Longest probe, which also keeps the duration well clear of what a `Duration` can hold.
+const MAX_PROBE_SECONDS: f64 = 3600.;
+
+/// Diagnostics that run instead of the GUI.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subcommand {
+    ListDevices { json: bool },
+    Probe { seconds: f64, json: bool },
+}
+
+/// Options given on the command line, they win over the config file for this launch.
+#[derive(Debug, Default)]
+pub struct Cli {
+    /// Matched against device names and labels.
+    pub device: Option<Regex>,
+    pub host: Option<String>,
+    pub mode: Option<VisualMode>,
+    pub config: Option<PathBuf>,
+    pub background: Option<PathBuf>,
+    pub size: Option<(u32, u32)>,
+    /// Seed of the noise test signal.
+    pub seed: Option<u64>,
+    pub fullscreen: bool,
+    pub borderless: bool,
+    pub subcommand: Option<Subcommand>,
+}
+
+impl Cli {
+    /// Parses the process arguments, printing usage and exiting when they don't make sense.
+    pub fn parse() -> Cli {
+        let matches = command().get_matches();
+
+        let subcommand = match matches.subcommand() {
+            Some(("list-devices", sub)) => Some(Subcommand::ListDevices {
+                json: sub.get_flag("json"),
+            }),
+            Some(("probe", sub)) => Some(Subcommand::Probe {
+                seconds: *sub.get_one::<f64>("seconds").expect("has a default"),
+                json: sub.get_flag("json"),
+            }),
+            // Kept from before the subcommands existed.
+            _ if matches.get_flag("list-devices") => Some(Subcommand::ListDevices { json: false }),
+            _ => None,
+        };
+
+        // The probe takes its own device options, after the subcommand.
+        let devices = match matches.subcommand() {
+            Some(("probe", sub)) => sub,
+            _ => &matches,
+        };
+
+        Cli {
+            device: devices.get_one::<Regex>("device").cloned(),
+            host: devices.get_one::<String>("host").cloned(),
+            mode: matches.get_one::<VisualMode>("mode").copied(),
+            config: matches.get_one::<PathBuf>("config").cloned(),
+            background: matches.get_one::<PathBuf>("background").cloned(),
+            size: matches.get_one::<(u32, u32)>("size").copied(),
+            seed: matches.get_one::<u64>("seed").copied(),
+            fullscreen: matches.get_flag("fullscreen"),
+            borderless: matches.get_flag("borderless"),
+            subcommand,
+        }
+    }
+
+    pub fn run(&self, subcommand: Subcommand) -> source::Result<()> {
+        match subcommand {
+            Subcommand::ListDevices { json } => {
+                let hosts = diagnostics::list_devices();
+
+                if json {
+                    print_json(&hosts);
+                } else {
+                    diagnostics::print_devices(&hosts);
+                }
+            }
+            Subcommand::Probe { seconds, json } => {
+                // Without any device options, probe what a recording app would pick.
+                let host = self.host.clone().or_else(|| {
+                    self.device
+                        .is_none()
+                        .then(|| cpal::default_host().id().name().to_string())
+                });
+
+                let selection = find_device(self.device.as_ref(), host.as_deref())?;
+                let report = diagnostics::probe(&selection, seconds)?;
+
+                if json {
+                    print_json(&report);
+                } else {
+                    diagnostics::print_probe(&report);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies the options that are also stored in the config file.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(mode) = self.mode {
+            config.visual_mode = mode;
+        }
+
+        if let Some(ref background) = self.background {
+            config.background = Some(background.clone());
+        }
+
+        if let Some((width, height)) = self.size {
+            config.window.width = width;
+            config.window.height = height;
+        }
+
+        if let Some(seed) = self.seed {
+            config.generator.seed = seed;
+        }
+    }
+
+    /// The device to open on launch, if any was asked for.
+    pub fn device(&self) -> source::Result<Option<DeviceSelection>> {
+        if self.device.is_none() && self.host.is_none() {
+            return Ok(None);
+        }
+
+        find_device(self.device.as_ref(), self.host.as_deref()).map(Some)
+    }
+}
+
+/// The first device matching `pattern`, restricted to `host` when given. With only a host, its
+/// default input is used.
+fn find_device(pattern: Option<&Regex>, host: Option<&str>) -> source::Result<DeviceSelection> {
+    let hosts = source::list_devices();
+    let monitors = source::list_monitors();
+
+    let on_host =
+        |device: &&DeviceInfo| host.is_none_or(|x| device.id.host.name().eq_ignore_ascii_case(x));
+
+    let found = match pattern {
+        Some(pattern) => hosts
+            .iter()
+            .flat_map(|x| x.inputs.iter().chain(&x.outputs))
+            .chain(&monitors)
+            .filter(on_host)
+            .find(|x| pattern.is_match(&x.id.name) || pattern.is_match(&x.label)),
+        None => hosts
+            .iter()
+            .flat_map(|x| &x.inputs)
+            .filter(on_host)
+            .find(|x| x.is_default),
+    };
+
+    match found {
+        Some(device) => Ok(DeviceSelection {
+            id: device.id.clone(),
+            config: None,
+        }),
+        None => Err(CaptureError::DeviceNotFound(
+            pattern.map_or_else(|| "default input".to_string(), |x| x.to_string()),
+        )),
+    }
+}
+
+fn print_json(value: &impl Serialize) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{json}"),
+        Err(err) => eprintln!("failed to write JSON: {err}"),
+    }
+}
+
+fn command() -> Command {
+    Command::new("stream-intro")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Audio visualizer for stream intros")
+        .args(device_args(
+            "Opens the first device whose name matches on launch",
+        ))
+        .arg(
+            Arg::new("mode")
+                .long("mode")
+                .short('m')
+                .value_name("MODE")
+                .value_parser(value_parser!(VisualMode))
+                .help("Visual to show"),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .short('c')
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .help("Config file to load and save instead of the default one"),
+        )
+        .arg(
+            Arg::new("background")
+                .long("background")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .help("Background image"),
+        )
+        .arg(
+            Arg::new("size")
+                .long("size")
+                .value_name("WIDTHxHEIGHT")
+                .value_parser(parse_size)
+                .help("Window size, e.g. 1920x1080"),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("SEED")
+                .value_parser(value_parser!(u64))
+                .help("Seed of the noise test signal, for repeatable captures"),
+        )
+        .arg(
+            Arg::new("fullscreen")
+                .long("fullscreen")
+                .short('f')
+                .action(ArgAction::SetTrue)
+                .help("Starts in fullscreen"),
+        )
+        .arg(
+            Arg::new("borderless")
+                .long("borderless")
+                .action(ArgAction::SetTrue)
+                .help("Opens the window without decorations"),
+        )
+        .arg(
+            Arg::new("list-devices")
+                .long("list-devices")
+                .action(ArgAction::SetTrue)
+                .help("Same as the list-devices subcommand"),
+        )
+        .subcommand(
+            Command::new("list-devices")
+                .about("Prints every host and device with their default and supported configs")
+                .arg(json_arg()),
+        )
+        .subcommand(
+            Command::new("probe")
+                .about("Captures from a device for a while and reports what arrives")
+                .args(device_args(
+                    "Probes the first device whose name matches instead of the default input",
+                ))
+                .arg(
+                    Arg::new("seconds")
+                        .long("seconds")
+                        .short('s')
+                        .value_name("SECONDS")
+                        .value_parser(parse_seconds)
+                        .default_value("5")
+                        .help("How long to capture for"),
+                )
+                .arg(json_arg()),
+        )
+}
+
+fn device_args(help: &'static str) -> [Arg; 2] {
+    [
+        Arg::new("device")
+            .long("device")
+            .short('d')
+            .value_name("REGEX")
+            .value_parser(|x: &str| Regex::new(x))
+            .help(help),
+        Arg::new("host")
+            .long("host")
+            .value_name("HOST")
+            .help("Only looks for the device on this audio host, e.g. ALSA or JACK"),
+    ]
+}
+
+fn json_arg() -> Arg {
+    Arg::new("json")
+        .long("json")
+        .action(ArgAction::SetTrue)
+        .help("Prints JSON instead of text")
+}
+
+fn parse_seconds(seconds: &str) -> Result<f64, String> {
+    match seconds.trim().parse::<f64>() {
+        Ok(seconds) if seconds > 0. && seconds <= MAX_PROBE_SECONDS => Ok(seconds),
+        _ => Err(format!(
+            "expected a number of seconds up to {MAX_PROBE_SECONDS}, got \"{seconds}\""
+        )),
+    }
+}
+
+fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("expected WIDTHxHEIGHT, got \"{size}\"");
+    let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+
+    match (width.trim().parse(), height.trim().parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(invalid()),
+    }
+}
+
+impl ValueEnum for VisualMode {
+    fn value_variants<'a>() -> &'a [Self] {
+        &VisualMode::ALL
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(PossibleValue::new(match self {
+            VisualMode::Waveform => "waveform",
+            VisualMode::Spectrum => "spectrum",
+            VisualMode::Spectrogram => "spectrogram",
+            VisualMode::Radial => "radial",
+            VisualMode::Goniometer => "goniometer",
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("1920x1080"), Ok((1920, 1080)));
+        assert_eq!(parse_size("640X480"), Ok((640, 480)));
+        assert_eq!(parse_size(" 800 x 600 "), Ok((800, 600)));
+
+        for invalid in ["", "1920", "0x1080", "1920x", "-1x2", "axb", "1x2x3"] {
+            assert!(parse_size(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn probe_seconds_stay_in_range() {
+        assert_eq!(parse_seconds("2.5"), Ok(2.5));
+        assert_eq!(parse_seconds("3600"), Ok(3600.));
+
+        for invalid in ["0", "-1", "inf", "NaN", "1e30", "five"] {
+            assert!(parse_seconds(invalid).is_err(), "{invalid}");
+        }
+    }
+}
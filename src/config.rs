@@ -0,0 +1,413 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use cpal::SampleFormat;
+use iced::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::background::BackgroundFit;
+use crate::source::{
+    BufferChoice, CaptureConfig, DeviceId, DeviceKind, DeviceSelection, GeneratorConfig,
+};
+use crate::waveform_style::WaveformStyle;
+use crate::VisualMode;
+
+const CONFIG_FILE: &str = "config.toml";
+
+const SAMPLE_FORMATS: [SampleFormat; 10] = [
+    SampleFormat::I8,
+    SampleFormat::I16,
+    SampleFormat::I32,
+    SampleFormat::I64,
+    SampleFormat::U8,
+    SampleFormat::U16,
+    SampleFormat::U32,
+    SampleFormat::U64,
+    SampleFormat::F32,
+    SampleFormat::F64,
+];
+
+/// Everything remembered between launches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub device: Option<SavedDevice>,
+    pub visual_mode: VisualMode,
+    pub waveform_style: WaveformStyle,
+    /// Background of the visualizer page, keyed out in OBS.
+    #[serde(with = "hex_color")]
+    pub chroma_color: Color,
+    pub background: Option<PathBuf>,
+    pub background_fit: BackgroundFit,
+    pub background_opacity: f32,
+    /// Test signal settings, the signal included.
+    pub generator: GeneratorConfig,
+    pub window: WindowGeometry,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            device: None,
+            visual_mode: VisualMode::default(),
+            waveform_style: WaveformStyle::default(),
+            chroma_color: Color::from_rgb(0., 1., 0.),
+            background: None,
+            background_fit: BackgroundFit::default(),
+            background_opacity: 1.,
+            generator: GeneratorConfig::default(),
+            window: WindowGeometry::default(),
+        }
+    }
+}
+
+impl Config {
+    /// `stream-intro/config.toml` in the user's config directory, e.g. `~/.config` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|x| x.join("stream-intro").join(CONFIG_FILE))
+    }
+
+    /// Reads the config at `path`, falling back to the defaults when there is none yet. A file
+    /// that can't be parsed is reported and ignored rather than stopping the app from starting.
+    pub fn load(path: &Path) -> Config {
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|err| {
+                eprintln!("ignoring invalid config {}: {}", path.display(), err);
+                Config::default()
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(err) => {
+                eprintln!("failed to read config {}: {}", path.display(), err);
+                Config::default()
+            }
+        }
+    }
+
+    /// `current` with every setting that is the same as at `launch` taken from `self` instead,
+    /// so command line options, which only last for one launch, aren't saved unless the user
+    /// changed them afterwards.
+    pub fn with_changes(&self, launch: &Config, current: &Config) -> Config {
+        fn pick<T: Clone + PartialEq>(saved: &T, launch: &T, current: &T) -> T {
+            if current == launch { saved } else { current }.clone()
+        }
+
+        Config {
+            device: pick(&self.device, &launch.device, &current.device),
+            visual_mode: pick(&self.visual_mode, &launch.visual_mode, &current.visual_mode),
+            waveform_style: pick(
+                &self.waveform_style,
+                &launch.waveform_style,
+                &current.waveform_style,
+            ),
+            chroma_color: pick(
+                &self.chroma_color,
+                &launch.chroma_color,
+                &current.chroma_color,
+            ),
+            background: pick(&self.background, &launch.background, &current.background),
+            background_fit: pick(
+                &self.background_fit,
+                &launch.background_fit,
+                &current.background_fit,
+            ),
+            background_opacity: pick(
+                &self.background_opacity,
+                &launch.background_opacity,
+                &current.background_opacity,
+            ),
+            generator: pick(&self.generator, &launch.generator, &current.generator),
+            window: WindowGeometry {
+                width: pick(
+                    &self.window.width,
+                    &launch.window.width,
+                    &current.window.width,
+                ),
+                height: pick(
+                    &self.window.height,
+                    &launch.window.height,
+                    &current.window.height,
+                ),
+                position: pick(
+                    &self.window.position,
+                    &launch.window.position,
+                    &current.window.position,
+                ),
+            },
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let contents = toml::to_string_pretty(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Written next to the config and renamed over it, so a crash mid-write can't leave a
+        // truncated config behind.
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let temporary = PathBuf::from(temporary);
+
+        fs::write(&temporary, contents)
+            .and_then(|_| fs::rename(&temporary, path))
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&temporary);
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowGeometry {
+    pub width: u32,
+    pub height: u32,
+    /// Position of the window, left to the window manager when `None`.
+    pub position: Option<(i32, i32)>,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        // Same as iced's default window size.
+        WindowGeometry {
+            width: 1024,
+            height: 768,
+            position: None,
+        }
+    }
+}
+
+/// A [`DeviceSelection`] in a form that survives a restart, with the host and sample format
+/// stored by name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedDevice {
+    pub host: String,
+    pub kind: DeviceKind,
+    pub name: String,
+    pub config: Option<SavedCaptureConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedCaptureConfig {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub sample_format: String,
+    /// Buffer size in frames, the device default when `None`.
+    pub buffer_size: Option<u32>,
+}
+
+impl From<&DeviceSelection> for SavedDevice {
+    fn from(selection: &DeviceSelection) -> Self {
+        SavedDevice {
+            host: selection.id.host.name().to_string(),
+            kind: selection.id.kind,
+            name: selection.id.name.clone(),
+            config: selection.config.map(|config| SavedCaptureConfig {
+                channels: config.channels,
+                sample_rate: config.sample_rate,
+                sample_format: config.sample_format.to_string(),
+                buffer_size: match config.buffer_size {
+                    BufferChoice::Default => None,
+                    BufferChoice::Frames(frames) => Some(frames),
+                },
+            }),
+        }
+    }
+}
+
+impl SavedDevice {
+    /// Turns the saved device back into a selection, or `None` if its host isn't available on
+    /// this machine. A sample format that no longer parses falls back to the device default.
+    pub fn selection(&self) -> Option<DeviceSelection> {
+        let host = cpal::available_hosts()
+            .into_iter()
+            .find(|x| x.name() == self.host)?;
+
+        let config = self.config.as_ref().and_then(|config| {
+            let sample_format = SAMPLE_FORMATS
+                .into_iter()
+                .find(|x| x.to_string() == config.sample_format)?;
+
+            Some(CaptureConfig {
+                channels: config.channels,
+                sample_rate: config.sample_rate,
+                sample_format,
+                buffer_size: config
+                    .buffer_size
+                    .map_or(BufferChoice::Default, BufferChoice::Frames),
+            })
+        });
+
+        Some(DeviceSelection {
+            id: DeviceId {
+                host,
+                kind: self.kind,
+                name: self.name.clone(),
+            },
+            config,
+        })
+    }
+}
+
+/// Stores colors as `#rrggbb`, which is easier to edit by hand than float components.
+pub mod hex_color {
+    use iced::Color;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::waveform_style;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&waveform_style::to_hex(*color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+
+        waveform_style::parse_hex(&hex)
+            .ok_or_else(|| de::Error::custom(format!("invalid color \"{hex}\"")))
+    }
+
+    /// Same as the parent module for an optional color, `None` is left out of the file.
+    pub mod option {
+        use iced::Color;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            color: &Option<Color>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match color {
+                Some(color) => super::serialize(color, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Color>, D::Error> {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] Color);
+
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(color)| color))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_toml() {
+        let config = Config {
+            device: Some(SavedDevice {
+                host: "ALSA".to_string(),
+                kind: DeviceKind::Monitor,
+                name: "alsa_output.pci.monitor".to_string(),
+                config: Some(SavedCaptureConfig {
+                    channels: 2,
+                    sample_rate: 48000,
+                    sample_format: "f32".to_string(),
+                    buffer_size: Some(512),
+                }),
+            }),
+            visual_mode: VisualMode::Goniometer,
+            chroma_color: Color::from_rgb8(0xff, 0x00, 0xff),
+            background: Some(PathBuf::from("/tmp/intro.png")),
+            background_fit: BackgroundFit::Tile,
+            background_opacity: 0.25,
+            window: WindowGeometry {
+                width: 1920,
+                height: 1080,
+                position: Some((-10, 40)),
+            },
+            ..Config::default()
+        };
+
+        let toml = toml::to_string_pretty(&config).unwrap();
+
+        assert_eq!(toml::from_str::<Config>(&toml).unwrap(), config);
+    }
+
+    #[test]
+    fn missing_settings_fall_back_to_defaults() {
+        let config: Config = toml::from_str("visual_mode = \"Radial\"").unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                visual_mode: VisualMode::Radial,
+                ..Config::default()
+            }
+        );
+    }
+
+    #[test]
+    fn launch_options_are_not_saved() {
+        let saved = Config::default();
+        let launch = Config {
+            visual_mode: VisualMode::Radial,
+            window: WindowGeometry {
+                width: 1920,
+                height: 1080,
+                position: None,
+            },
+            ..saved.clone()
+        };
+        let current = Config {
+            chroma_color: Color::from_rgb8(0x00, 0xff, 0x00),
+            window: WindowGeometry {
+                position: Some((10, 20)),
+                ..launch.window
+            },
+            ..launch.clone()
+        };
+
+        assert_eq!(
+            saved.with_changes(&launch, &current),
+            Config {
+                chroma_color: Color::from_rgb8(0x00, 0xff, 0x00),
+                window: WindowGeometry {
+                    position: Some((10, 20)),
+                    ..saved.window
+                },
+                ..saved.clone()
+            }
+        );
+    }
+
+    #[test]
+    fn changed_launch_options_are_saved() {
+        let saved = Config::default();
+        let launch = Config {
+            visual_mode: VisualMode::Radial,
+            ..saved.clone()
+        };
+        let current = Config {
+            visual_mode: VisualMode::Spectrum,
+            ..launch.clone()
+        };
+
+        assert_eq!(saved.with_changes(&launch, &current), current);
+    }
+
+    #[test]
+    fn saves_over_the_old_config_without_leaving_a_temporary_file() {
+        let dir = std::env::temp_dir().join(format!("stream-intro-config-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        let config = Config {
+            visual_mode: VisualMode::Radial,
+            ..Config::default()
+        };
+
+        Config::default().save(&path).unwrap();
+        config.save(&path).unwrap();
+
+        assert_eq!(Config::load(&path), config);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
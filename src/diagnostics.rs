@@ -0,0 +1,404 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cpal::{SupportedBufferSize, SupportedStreamConfigRange};
+use iced::futures::channel::mpsc;
+use serde::Serialize;
+
+use crate::source::{
+    self, AudioSource, CaptureConfig, DeviceInfo, DeviceKind, DeviceSelection, DeviceSource,
+    EventSender, SourceEvent,
+};
+
+/// How often the probe looks for new samples. Callbacks are timed by the sample ring, but ones
+/// closer together than this are only seen as their average.
+const PROBE_POLL: Duration = Duration::from_millis(1);
+/// Levels below this are reported as silence.
+const SILENCE_DB: f64 = -120.;
+
+#[derive(Debug, Serialize)]
+pub struct HostReport {
+    pub host: String,
+    pub devices: Vec<DeviceReport>,
+    /// Why the devices couldn't be listed, if they couldn't.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeviceReport {
+    pub kind: DeviceKind,
+    pub name: String,
+    pub label: String,
+    pub is_default: bool,
+    pub default_config: Option<ConfigReport>,
+    pub supported_configs: Vec<RangeReport>,
+    /// Why the configs couldn't be read, if they couldn't.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigReport {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub sample_format: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RangeReport {
+    pub channels: u16,
+    pub sample_format: String,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    /// Buffer size range in frames, `None` when the host doesn't say.
+    pub buffer_size: Option<(u32, u32)>,
+}
+
+impl From<CaptureConfig> for ConfigReport {
+    fn from(config: CaptureConfig) -> Self {
+        ConfigReport {
+            channels: config.channels,
+            sample_rate: config.sample_rate,
+            sample_format: config.sample_format.to_string(),
+        }
+    }
+}
+
+impl From<&SupportedStreamConfigRange> for RangeReport {
+    fn from(range: &SupportedStreamConfigRange) -> Self {
+        RangeReport {
+            channels: range.channels(),
+            sample_format: range.sample_format().to_string(),
+            min_sample_rate: range.min_sample_rate().0,
+            max_sample_rate: range.max_sample_rate().0,
+            buffer_size: match *range.buffer_size() {
+                SupportedBufferSize::Range { min, max } => Some((min, max)),
+                SupportedBufferSize::Unknown => None,
+            },
+        }
+    }
+}
+
+/// Every host and device the picker would offer, including the default and supported configs
+/// of each. Desktop audio monitors are listed as their own host.
+pub fn list_devices() -> Vec<HostReport> {
+    let mut hosts = source::list_devices()
+        .into_iter()
+        .map(|host| HostReport {
+            host: host.host.name().to_string(),
+            devices: host
+                .inputs
+                .iter()
+                .chain(&host.outputs)
+                .map(describe_device)
+                .collect(),
+            error: host.error,
+        })
+        .collect::<Vec<_>>();
+
+    let monitors = source::list_monitors();
+
+    if !monitors.is_empty() {
+        hosts.push(HostReport {
+            host: "Desktop audio".to_string(),
+            devices: monitors.iter().map(describe_device).collect(),
+            error: None,
+        });
+    }
+
+    hosts
+}
+
+fn describe_device(device: &DeviceInfo) -> DeviceReport {
+    let mut report = DeviceReport {
+        kind: device.id.kind,
+        name: device.id.name.clone(),
+        label: device.label.clone(),
+        is_default: device.is_default,
+        default_config: None,
+        supported_configs: Vec::new(),
+        error: None,
+    };
+
+    match source::supported_configs(&device.id) {
+        Ok(ranges) => report.supported_configs = ranges.iter().map(RangeReport::from).collect(),
+        Err(err) => report.error = Some(err.to_string()),
+    }
+
+    // A device without a usable format still lists its ranges, which is the interesting part.
+    report.default_config = source::default_capture_config(&device.id)
+        .ok()
+        .map(ConfigReport::from);
+
+    report
+}
+
+pub fn print_devices(hosts: &[HostReport]) {
+    for host in hosts {
+        println!("{}", host.host);
+
+        if let Some(ref err) = host.error {
+            println!("  error: {err}");
+        }
+
+        for device in &host.devices {
+            let default = if device.is_default { " (default)" } else { "" };
+
+            println!("  {:?}: {}{}", device.kind, device.label, default);
+
+            if let Some(ref config) = device.default_config {
+                println!(
+                    "    default   {} ch, {} Hz, {}",
+                    config.channels, config.sample_rate, config.sample_format
+                );
+            }
+
+            for range in &device.supported_configs {
+                let buffer = match range.buffer_size {
+                    Some((min, max)) => format!("{min}-{max} frames"),
+                    None => "any buffer".to_string(),
+                };
+
+                println!(
+                    "    supports  {} ch, {}, {}-{} Hz, {}",
+                    range.channels,
+                    range.sample_format,
+                    range.min_sample_rate,
+                    range.max_sample_rate,
+                    buffer
+                );
+            }
+
+            if let Some(ref err) = device.error {
+                println!("    error     {err}");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProbeReport {
+    pub device: String,
+    pub host: String,
+    pub seconds: f64,
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// Frames actually delivered per second, which drifts from `sample_rate` when the device
+    /// clock or the host's resampling is off.
+    pub measured_sample_rate: f64,
+    pub frames: usize,
+    pub levels: Vec<LevelReport>,
+    pub callbacks: usize,
+    pub frames_per_callback: Option<Summary>,
+    pub callback_interval_ms: Option<Summary>,
+    /// Errors the stream reported, including ones it recovered from such as overruns.
+    pub stream_errors: usize,
+    /// Why the stream died, if it did before the probe was over.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_lost: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LevelReport {
+    pub rms_db: f64,
+    pub peak_db: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Summary {
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Default)]
+struct Accumulator {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: usize,
+}
+
+impl Accumulator {
+    fn add(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        }
+
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn summary(&self) -> Option<Summary> {
+        (self.count > 0).then(|| Summary {
+            min: self.min,
+            mean: self.sum / self.count as f64,
+            max: self.max,
+        })
+    }
+}
+
+/// Captures from the device for `seconds` and measures what actually arrives.
+///
+/// Callbacks are timestamped by the sample ring as they push, so the probe sees the device
+/// exactly as the visualizer does.
+pub fn probe(selection: &DeviceSelection, seconds: f64) -> source::Result<ProbeReport> {
+    let (events, mut lost) = mpsc::channel(1);
+    let mut source = DeviceSource::new(selection, Some(EventSender::new(0, events)))?;
+    source.start()?;
+
+    let samples = source.samples();
+    let channels = source.channels().max(1) as usize;
+
+    let mut squares = vec![0f64; channels];
+    let mut peaks = vec![0f64; channels];
+    let mut chunk = Vec::new();
+    let mut callback_frames = Accumulator::default();
+    let mut intervals = Accumulator::default();
+
+    let mut frames = 0;
+    let mut last_written = samples.written();
+    let (mut last_pushes, _) = samples.pushes();
+    let mut first_callback: Option<(Duration, usize)> = None;
+    let mut last_callback: Option<Duration> = None;
+    let mut stream_lost = None;
+
+    let start = Instant::now();
+    let duration = Duration::from_secs_f64(seconds);
+
+    while start.elapsed() < duration {
+        thread::sleep(PROBE_POLL);
+
+        if let Ok(Some((_, SourceEvent::StreamLost(reason)))) = lost.try_next() {
+            stream_lost = Some(reason);
+            break;
+        }
+
+        let written = samples.written();
+        let (pushes, pushed_at) = samples.pushes();
+
+        if written == last_written || pushes == last_pushes {
+            continue;
+        }
+
+        let new = written - last_written;
+        let callbacks = pushes - last_pushes;
+        last_written = written;
+        last_pushes = pushes;
+
+        samples.snapshot(&mut chunk, new);
+        chunk.drain(..chunk.len() % channels);
+
+        for (i, sample) in chunk.iter().enumerate() {
+            let sample = f64::from(*sample);
+
+            squares[i % channels] += sample * sample;
+            peaks[i % channels] = peaks[i % channels].max(sample.abs());
+        }
+
+        frames += new / channels;
+
+        // Several callbacks since the last poll only tell their average.
+        for _ in 0..callbacks {
+            callback_frames.add((new / channels) as f64 / callbacks as f64);
+
+            if let Some(last) = last_callback {
+                intervals.add((pushed_at - last).as_secs_f64() * 1000. / callbacks as f64);
+            }
+        }
+
+        last_callback = Some(pushed_at);
+        first_callback.get_or_insert((pushed_at, frames));
+    }
+
+    source.stop()?;
+
+    // Measured from the first callback on, so the time it takes the stream to start up
+    // doesn't count.
+    let measured_sample_rate = match (first_callback, last_callback) {
+        (Some((first, first_frames)), Some(last)) if last > first => {
+            (frames - first_frames) as f64 / (last - first).as_secs_f64()
+        }
+        _ => 0.,
+    };
+
+    Ok(ProbeReport {
+        device: selection.id.name.clone(),
+        host: selection.id.host.name().to_string(),
+        seconds,
+        channels: source.channels(),
+        sample_rate: source.sample_rate(),
+        measured_sample_rate,
+        frames,
+        levels: squares
+            .iter()
+            .zip(&peaks)
+            .map(|(squares, peak)| LevelReport {
+                rms_db: to_db((squares / frames.max(1) as f64).sqrt()),
+                peak_db: to_db(*peak),
+            })
+            .collect(),
+        callbacks: callback_frames.count,
+        frames_per_callback: callback_frames.summary(),
+        callback_interval_ms: intervals.summary(),
+        stream_errors: source.stream_errors(),
+        stream_lost,
+    })
+}
+
+pub fn print_probe(report: &ProbeReport) {
+    println!(
+        "{} ({}), {:.1} s",
+        report.device, report.host, report.seconds
+    );
+    println!(
+        "  config      {} ch, {} Hz",
+        report.channels, report.sample_rate
+    );
+    println!(
+        "  measured    {:.0} Hz, {} frames",
+        report.measured_sample_rate, report.frames
+    );
+
+    if let (Some(frames), Some(interval)) =
+        (report.frames_per_callback, report.callback_interval_ms)
+    {
+        println!(
+            "  callbacks   {}, {:.0}-{:.0} frames (mean {:.1}), every {:.1}-{:.1} ms (mean {:.1})",
+            report.callbacks,
+            frames.min,
+            frames.max,
+            frames.mean,
+            interval.min,
+            interval.max,
+            interval.mean
+        );
+    } else {
+        println!("  callbacks   {}", report.callbacks);
+    }
+
+    if report.stream_errors > 0 {
+        println!("  errors      {}", report.stream_errors);
+    }
+
+    if let Some(ref reason) = report.stream_lost {
+        println!("  lost        {reason}");
+    }
+
+    for (channel, level) in report.levels.iter().enumerate() {
+        println!(
+            "  channel {:<3} RMS {:.1} dB, peak {:.1} dB",
+            channel + 1,
+            level.rms_db,
+            level.peak_db
+        );
+    }
+}
+
+fn to_db(level: f64) -> f64 {
+    (20. * level.log10()).max(SILENCE_DB)
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+
+/// Lowest level shown by the dB scale, anything quieter sits on the center line.
+const DB_FLOOR: f32 = -60.;
+/// Fraction of the canvas the auto gain aims to fill with the recent peak.
+const AUTO_GAIN_TARGET: f32 = 0.8;
+/// Keeps the auto gain from blowing up the noise floor during silence.
+const MAX_AUTO_GAIN_DB: f32 = 40.;
+/// Levels closer than this to the peak are snapped onto it, so the auto gain comes to rest.
+const SETTLED: f32 = 1e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AmplitudeScale {
+    #[default]
+    Linear,
+    Decibel,
+}
+
+impl AmplitudeScale {
+    pub const ALL: [AmplitudeScale; 2] = [AmplitudeScale::Linear, AmplitudeScale::Decibel];
+}
+
+impl fmt::Display for AmplitudeScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AmplitudeScale::Linear => "Linear",
+            AmplitudeScale::Decibel => "Decibel",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GainConfig {
+    /// Manual gain, applied on top of the auto gain.
+    pub gain_db: f32,
+    pub auto: bool,
+    /// How quickly the auto gain backs off when the level rises.
+    pub attack_ms: f32,
+    /// How quickly the auto gain recovers once the level drops.
+    pub release_ms: f32,
+    pub scale: AmplitudeScale,
+    /// Rounds loud peaks off with `tanh` instead of cutting them at the canvas edge.
+    pub soft_clip: bool,
+}
+
+impl Default for GainConfig {
+    fn default() -> Self {
+        GainConfig {
+            gain_db: 0.,
+            auto: false,
+            attack_ms: 10.,
+            release_ms: 1000.,
+            scale: AmplitudeScale::default(),
+            soft_clip: false,
+        }
+    }
+}
+
+/// Tracks the recent peak level of the displayed samples to drive the auto gain.
+#[derive(Debug, Default)]
+pub struct AutoGain {
+    pub config: GainConfig,
+    level: f32,
+}
+
+impl AutoGain {
+    pub fn new(config: GainConfig) -> Self {
+        AutoGain { config, level: 0. }
+    }
+
+    /// Follows the peak of `samples`, `elapsed` seconds after the previous update. Returns
+    /// whether the auto gain visibly changed.
+    pub fn update(&mut self, samples: &[f32], elapsed: f32) -> bool {
+        let peak = samples.iter().fold(0., |peak: f32, v| peak.max(v.abs()));
+        let time = if peak > self.level {
+            self.config.attack_ms
+        } else {
+            self.config.release_ms
+        };
+        let coefficient = (-elapsed * 1000. / time.max(1.)).exp();
+        let previous = self.level;
+
+        self.level = if (peak - self.level).abs() > SETTLED {
+            peak + (self.level - peak) * coefficient
+        } else {
+            peak
+        };
+
+        self.config.auto && self.level != previous
+    }
+
+    pub fn reset(&mut self) {
+        self.level = 0.;
+    }
+
+    pub fn mapping(&self) -> GainMapping {
+        let mut gain = db_to_gain(self.config.gain_db);
+
+        if self.config.auto {
+            gain *= (AUTO_GAIN_TARGET / self.level).min(db_to_gain(MAX_AUTO_GAIN_DB));
+        }
+
+        GainMapping {
+            gain,
+            scale: self.config.scale,
+            soft_clip: self.config.soft_clip,
+        }
+    }
+}
+
+/// Turns a sample into a position between -1 and 1 on the canvas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GainMapping {
+    gain: f32,
+    scale: AmplitudeScale,
+    soft_clip: bool,
+}
+
+impl GainMapping {
+    pub fn map(&self, sample: f32) -> f32 {
+        let value = sample * self.gain;
+
+        let value = match self.scale {
+            AmplitudeScale::Linear => value,
+            AmplitudeScale::Decibel => {
+                let db = 20. * value.abs().max(f32::MIN_POSITIVE).log10();
+
+                value.signum() * ((db - DB_FLOOR) / -DB_FLOOR).max(0.)
+            }
+        };
+
+        if self.soft_clip {
+            value.tanh()
+        } else {
+            value.clamp(-1., 1.)
+        }
+    }
+}
+
+fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(gain_db: f32, scale: AmplitudeScale, soft_clip: bool) -> GainMapping {
+        AutoGain::new(GainConfig {
+            gain_db,
+            scale,
+            soft_clip,
+            ..GainConfig::default()
+        })
+        .mapping()
+    }
+
+    #[test]
+    fn linear_applies_gain_and_clamps() {
+        let mapping = mapping(20., AmplitudeScale::Linear, false);
+
+        assert!((mapping.map(0.05) - 0.5).abs() < 1e-5);
+        assert_eq!(mapping.map(0.5), 1.);
+        assert_eq!(mapping.map(-0.5), -1.);
+    }
+
+    #[test]
+    fn soft_clip_stays_inside_the_canvas() {
+        let mapping = mapping(20., AmplitudeScale::Linear, true);
+
+        assert!(mapping.map(0.5) < 1.);
+        assert!(mapping.map(0.5) > mapping.map(0.2));
+        assert_eq!(mapping.map(-0.5), -mapping.map(0.5));
+    }
+
+    #[test]
+    fn decibel_scale_spans_the_floor_to_full_scale() {
+        let mapping = mapping(0., AmplitudeScale::Decibel, false);
+
+        assert_eq!(mapping.map(1.), 1.);
+        // -30 dB is half way up from the -60 dB floor.
+        assert!((mapping.map(-10f32.powf(-1.5)) + 0.5).abs() < 1e-5);
+        assert_eq!(mapping.map(0.), 0.);
+    }
+
+    #[test]
+    fn auto_gain_settles() {
+        let mut auto_gain = AutoGain::new(GainConfig {
+            auto: true,
+            ..GainConfig::default()
+        });
+
+        let mut steps = 0;
+
+        while auto_gain.update(&[0.5, -0.25], 0.1) {
+            steps += 1;
+            assert!(steps < 1000, "never settled");
+        }
+
+        assert!((auto_gain.mapping().map(0.5) - AUTO_GAIN_TARGET).abs() < 1e-3);
+    }
+}
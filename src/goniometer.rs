@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use iced::widget::canvas::{path, stroke::Stroke, Frame, Text};
+use iced::{Color, Point, Rectangle, Size, Vector};
+
+use crate::sample_ring::SampleRing;
+use crate::visual::Visual;
+
+/// Most frames taken from the ring per tick, so a long stall doesn't produce a huge path.
+const MAX_FRAMES_PER_TICK: usize = 4096;
+/// Number of previous ticks still drawn, fading out like phosphor on a scope.
+const PERSISTENCE: usize = 8;
+/// How much of the previous correlation reading is kept each tick.
+const CORRELATION_SMOOTHING: f32 = 0.9;
+const METER_HEIGHT: f32 = 12.;
+
+const TRACE_COLOR: Color = Color::BLACK;
+const AXIS_COLOR: Color = Color::from_rgba(0., 0., 0., 0.3);
+const IN_PHASE_COLOR: Color = Color::from_rgb(0., 0.5, 0.);
+const OUT_OF_PHASE_COLOR: Color = Color::from_rgb(0.8, 0., 0.);
+
+/// Recent stereo samples rotated into mid/side space, plus a running phase correlation.
+#[derive(Debug, Default)]
+pub struct GoniometerHistory {
+    /// Points per tick, oldest first. `x` is side and `y` is mid, both scaled so full scale is 1.
+    frames: VecDeque<Vec<(f32, f32)>>,
+    /// Correlation between left and right, from -1 (out of phase) to 1 (mono).
+    correlation: f32,
+    last_written: usize,
+    samples: Vec<f32>,
+}
+
+impl GoniometerHistory {
+    /// Pulls in everything written to `ring` since the last update.
+    pub fn update(&mut self, ring: &SampleRing, channels: u16) {
+        let channels = channels.max(1) as usize;
+        let written = ring.written();
+        let new_frames = (written.saturating_sub(self.last_written) / channels)
+            .min(MAX_FRAMES_PER_TICK)
+            .min(ring.capacity() / channels);
+
+        self.last_written = ring.snapshot(&mut self.samples, new_frames * channels);
+        self.samples.drain(..self.samples.len() % channels);
+
+        let (mut lr, mut ll, mut rr) = (0., 0., 0.);
+        let points = self
+            .samples
+            .chunks_exact(channels)
+            .map(|frame| {
+                let left = frame[0];
+                let right = frame[1.min(channels - 1)];
+
+                lr += left * right;
+                ll += left * left;
+                rr += right * right;
+
+                (
+                    (right - left) * FRAC_1_SQRT_2,
+                    (left + right) * FRAC_1_SQRT_2,
+                )
+            })
+            .collect();
+
+        if self.frames.len() == PERSISTENCE {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(points);
+
+        // Silence has no phase, leave the meter where it was.
+        let energy: f32 = ll * rr;
+        if energy > f32::EPSILON {
+            let correlation = lr / energy.sqrt();
+            self.correlation = self.correlation * CORRELATION_SMOOTHING
+                + correlation * (1. - CORRELATION_SMOOTHING);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = GoniometerHistory::default();
+    }
+}
+
+pub struct Goniometer<'a> {
+    pub history: &'a GoniometerHistory,
+}
+
+impl<'a> Visual for Goniometer<'a> {
+    fn draw(&self, frame: &mut Frame) {
+        let bounds = Rectangle::with_size(frame.size());
+
+        // Leave room underneath for the correlation meter and its label.
+        let scope_height = (bounds.height - METER_HEIGHT * 4.).max(0.);
+        let center = Point::new(bounds.width / 2., scope_height / 2.);
+        // Full scale on both channels reaches the edge of the scope.
+        let scale = bounds.width.min(scope_height) / 2. * FRAC_1_SQRT_2;
+        let reach = scale / FRAC_1_SQRT_2;
+
+        let axes = path::Path::new(|builder| {
+            for (dx, dy) in [(0., 1.), (1., 1.), (-1., 1.)] {
+                builder.move_to(center + Vector::new(-dx * reach, -dy * reach));
+                builder.line_to(center + Vector::new(dx * reach, dy * reach));
+            }
+        });
+        frame.stroke(&axes, Stroke::default().with_color(AXIS_COLOR));
+
+        let frames = self.history.frames.len();
+        for (age, points) in self.history.frames.iter().rev().enumerate() {
+            if points.is_empty() {
+                continue;
+            }
+
+            let trace = path::Path::new(|builder| {
+                for (i, (side, mid)) in points.iter().enumerate() {
+                    let point = center + Vector::new(side * scale, -mid * scale);
+
+                    if i == 0 {
+                        builder.move_to(point);
+                    } else {
+                        builder.line_to(point);
+                    }
+                }
+            });
+            let color = Color {
+                a: 1. - age as f32 / frames as f32,
+                ..TRACE_COLOR
+            };
+
+            frame.stroke(&trace, Stroke::default().with_color(color));
+        }
+
+        self.draw_meter(frame, bounds, scope_height + METER_HEIGHT);
+    }
+}
+
+impl<'a> Goniometer<'a> {
+    fn draw_meter(&self, frame: &mut Frame, bounds: Rectangle, y: f32) {
+        let correlation = self.history.correlation.clamp(-1., 1.);
+        let width = bounds.width / 2.;
+        let center_x = bounds.width / 2.;
+        let left = center_x - width / 2.;
+
+        frame.fill_rectangle(
+            Point::new(left, y),
+            Size::new(width, METER_HEIGHT),
+            AXIS_COLOR,
+        );
+
+        let (x, color) = if correlation < 0. {
+            (center_x + correlation * width / 2., OUT_OF_PHASE_COLOR)
+        } else {
+            (center_x, IN_PHASE_COLOR)
+        };
+        frame.fill_rectangle(
+            Point::new(x, y),
+            Size::new(correlation.abs() * width / 2., METER_HEIGHT),
+            color,
+        );
+
+        frame.fill_text(Text {
+            content: format!("Correlation {correlation:+.2}"),
+            position: Point::new(center_x, y + METER_HEIGHT + 2.),
+            horizontal_alignment: iced::alignment::Horizontal::Center,
+            ..Text::default()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds the same stereo block through the goniometer until the correlation has settled.
+    fn settle(block: &[f32]) -> GoniometerHistory {
+        let ring = SampleRing::new(1 << 12);
+        let mut history = GoniometerHistory::default();
+
+        for _ in 0..200 {
+            ring.push_slice(block);
+            history.update(&ring, 2);
+        }
+
+        history
+    }
+
+    fn stereo(left: impl Fn(f32) -> f32, right: impl Fn(f32) -> f32) -> Vec<f32> {
+        (0..256)
+            .map(|i| (i as f32 / 16.).sin())
+            .flat_map(|x| [left(x), right(x)])
+            .collect()
+    }
+
+    #[test]
+    fn mono_is_correlated_and_vertical() {
+        let history = settle(&stereo(|x| x, |x| x));
+
+        assert!(history.correlation > 0.99);
+        for (side, _) in history.frames.iter().flatten() {
+            assert!(side.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn inverted_channels_are_anticorrelated_and_horizontal() {
+        let history = settle(&stereo(|x| x, |x| -x));
+
+        assert!(history.correlation < -0.99);
+        for (_, mid) in history.frames.iter().flatten() {
+            assert!(mid.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn silence_leaves_the_meter_alone() {
+        let history = settle(&[0.; 512]);
+
+        assert_eq!(history.correlation, 0.);
+        assert!(history
+            .frames
+            .iter()
+            .flatten()
+            .all(|(side, mid)| *side == 0. && *mid == 0.));
+    }
+}
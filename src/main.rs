@@ -1,78 +1,117 @@
-use std::env;
-use std::sync::{mpsc, Arc};
+use std::f32::consts::TAU;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
 
-use cpal::platform::Host;
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Stream, StreamError};
+use cpal::SupportedStreamConfigRange;
 
-use iced::widget::canvas::{path, stroke::Stroke, Canvas, Cursor, Frame, Geometry, Program};
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, StreamExt};
+use iced::widget::canvas::{Cache, Canvas};
 use iced::widget::{
-    self, button, column, container, horizontal_rule, horizontal_space, image, row, scrollable,
-    text, vertical_space,
+    self, button, checkbox, column, container, horizontal_rule, horizontal_space, image, pick_list,
+    row, scrollable, slider, text, text_input, vertical_space,
 };
 use iced::{
-    executor, keyboard, subscription, theme, Alignment, Application, Color, Command, Element,
-    Event, Length, Point, Rectangle, Settings, Subscription, Theme,
+    executor, keyboard, subscription, theme, window, Alignment, Application, Color, Command,
+    ContentFit, Element, Event, Length, Settings, Subscription, Theme,
 };
 
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 
+mod background;
+mod cli;
+mod config;
+mod diagnostics;
+mod gain;
+mod goniometer;
 mod output_modal;
+mod radial;
+mod sample_ring;
+mod smoothing;
+mod source;
+mod spectrogram;
+mod spectrum;
+mod stack;
+mod visual;
+mod waveform;
+mod waveform_style;
 
+use background::{Background, BackgroundFit, IMAGE_EXTENSIONS};
+use cli::Cli;
+use config::{Config, SavedDevice, WindowGeometry};
+use gain::{AmplitudeScale, AutoGain, GainConfig};
+use goniometer::{Goniometer, GoniometerHistory};
 use output_modal::Modal;
+use radial::{Logo, Radial, RadialConfig, RadialData, RadialSource};
+use smoothing::{PeakHold, Smoothed, SmoothingConfig};
+use source::{
+    AudioSource, BufferChoice, CaptureConfig, CaptureError, DeviceId, DeviceInfo, DeviceSelection,
+    DeviceSource, EventSender, FileSource, GeneratorConfig, GeneratorSource, HostDevices, Signal,
+    SourceEvent,
+};
+use spectrogram::{Colormap, SpectrogramHistory, SPECTROGRAM_BANDS};
+use spectrum::{Spectrum, SpectrumAnalyzer, SpectrumConfig, SpectrumStyle, Window, FFT_SIZES};
+use stack::Stack;
+use visual::{Cached, Visual};
+use waveform::{ChannelMode, Envelope, Oscilloscope, TriggerConfig, TriggerEdge, Waveform};
+use waveform_style::{Cap, Join, WaveformStyle};
 
-static OUTPUT_SCROLLABLE_ID: Lazy<scrollable::Id> = Lazy::new(scrollable::Id::unique);
-
-struct Waveform {
-    rx: Arc<mpsc::Receiver<Vec<f32>>>,
-}
-
-impl<Message> Program<Message> for Waveform {
-    type State = ();
-
-    fn draw(
-        &self,
-        _state: &(),
-        _theme: &Theme,
-        bounds: Rectangle,
-        _cursor: Cursor,
-    ) -> Vec<Geometry> {
-        let data = self.rx.recv().unwrap();
-
-        let mut frame = Frame::new(bounds.size());
-
-        let mut path_builder = path::Builder::new();
-        let slice_width = bounds.width / data.len() as f32;
-        let mut x = 0.;
-
-        for (i, v) in data.iter().enumerate() {
-            let y = (v * bounds.height) / 2. + bounds.height / 2.;
-
-            if i == 0 {
-                path_builder.move_to(Point::new(x, y));
-            } else {
-                path_builder.line_to(Point::new(x, y));
-            }
-
-            x += slice_width;
-        }
+const DEFAULT_FPS: u32 = 60;
+/// How long the config has to stay unchanged before it is written, so dragging the window or
+/// typing doesn't write the file on every event.
+const CONFIG_SAVE_DELAY: Duration = Duration::from_secs(1);
 
-        let path = path_builder.build();
-        frame.stroke(
-            &path,
-            Stroke::default().with_color(Color::BLACK).with_width(2.),
-        );
+/// Gradient end color offered when a gradient is first switched on.
+const RIGHT_GRADIENT_DEFAULT: Color = Color::from_rgb(0.8, 0., 0.8);
 
-        vec![frame.into_geometry()]
-    }
-}
+static OUTPUT_SCROLLABLE_ID: Lazy<scrollable::Id> = Lazy::new(scrollable::Id::unique);
 
 #[derive(Debug, Clone)]
 pub enum Message {
     ShowOutputModal,
     HideOutputModal,
     Tick,
-    SelectedDevice(String),
+    SelectedDevice(DeviceSelection),
+    ToggleDeviceConfigs(DeviceId),
+    BufferChoiceChanged(BufferChoice),
+    AudioFilePathChanged(String),
+    SelectedAudioFile,
+    GeneratorConfigChanged(GeneratorConfig),
+    GeneratorSeedChanged(String),
+    SelectedSignal(Signal),
+    #[cfg(feature = "jack")]
+    JackClientNameChanged(String),
+    #[cfg(feature = "jack")]
+    JackPortsChanged(String),
+    #[cfg(feature = "jack")]
+    SelectedJack,
+    ChannelModeChanged(ChannelMode),
+    VisualModeChanged(VisualMode),
+    TargetFpsChanged(u32),
+    TriggerConfigChanged(TriggerConfig),
+    EnvelopeChanged(Envelope),
+    SmoothingConfigChanged(SmoothingConfig),
+    GainConfigChanged(GainConfig),
+    WaveformStyleChanged(WaveformStyle),
+    WaveformColorChanged(String),
+    WaveformGradientChanged(String),
+    ChromaColorChanged(String),
+    ColormapChanged(Colormap),
+    RadialConfigChanged(RadialConfig),
+    LogoPathChanged(String),
+    BackgroundPathChanged(String),
+    PickBackground,
+    BackgroundFitChanged(BackgroundFit),
+    BackgroundOpacityChanged(f32),
+    SpectrumConfigChanged(SpectrumConfig),
+    DismissError,
+    SourceEventsReady(mpsc::Sender<(u64, SourceEvent)>),
+    SourceEvent(u64, SourceEvent),
+    RetryDevice,
+    DeviceChecked(DeviceId, bool),
+    SaveConfig,
     Event(Event),
 }
 
@@ -80,7 +119,45 @@ pub enum Message {
 #[allow(dead_code)]
 pub enum Page {
     Main,
+    Visualizer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VisualMode {
+    #[default]
     Waveform,
+    Spectrum,
+    Spectrogram,
+    Radial,
+    Goniometer,
+}
+
+impl VisualMode {
+    pub const ALL: [VisualMode; 5] = [
+        VisualMode::Waveform,
+        VisualMode::Spectrum,
+        VisualMode::Spectrogram,
+        VisualMode::Radial,
+        VisualMode::Goniometer,
+    ];
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|x| *x == self).unwrap_or(0);
+
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+impl std::fmt::Display for VisualMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            VisualMode::Waveform => "Waveform",
+            VisualMode::Spectrum => "Spectrum",
+            VisualMode::Spectrogram => "Spectrogram",
+            VisualMode::Radial => "Radial",
+            VisualMode::Goniometer => "Goniometer",
+        })
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
@@ -91,6 +168,20 @@ enum Direction {
     Multi,
 }
 
+/// What the app starts with, read from the config file before the window opens.
+#[derive(Debug, Default)]
+pub struct Flags {
+    /// The config file as loaded.
+    config: Config,
+    /// `config` with the command line options applied, which the app starts with.
+    launch: Config,
+    /// Where changes are saved, nowhere when there is no config directory.
+    config_path: Option<PathBuf>,
+    /// Device asked for on the command line, opened instead of the saved one.
+    device: Option<DeviceSelection>,
+    fullscreen: bool,
+}
+
 #[allow(dead_code)]
 pub struct ScrollableData {
     width: u16,
@@ -103,26 +194,91 @@ pub struct ScrollableData {
 struct App {
     theme: Theme,
     show_output_modal: bool,
-    output_device_names: Vec<String>,
+    output_devices: Vec<HostDevices>,
+    monitors: Vec<DeviceInfo>,
+    expanded_device: Option<(DeviceId, Vec<SupportedStreamConfigRange>)>,
+    buffer_choice: BufferChoice,
     output_scrollable: ScrollableData,
     page: Page,
-    host: Host,
-    output_stream: Option<Stream>,
-    output_sender: mpsc::Sender<Vec<f32>>,
-    output_reciever: Arc<mpsc::Receiver<Vec<f32>>>,
+    source: Option<Box<dyn AudioSource>>,
+    source_events: Option<mpsc::Sender<(u64, SourceEvent)>>,
+    /// Counts the sources started so far. Events tagged with an older generation come from a
+    /// source that has since been replaced.
+    source_generation: u64,
+    selected_device: Option<DeviceSelection>,
+    lost_device: Option<DeviceSelection>,
+    /// Whether the lost device is being looked for, so retries don't pile up.
+    retrying: bool,
+    audio_file_path: String,
+    generator: GeneratorConfig,
+    /// Generator seed as typed, applied once it parses.
+    seed_input: String,
+    #[cfg(feature = "jack")]
+    jack: source::JackConfig,
+    /// Comma separated port names, kept as typed and split when the client starts.
+    #[cfg(feature = "jack")]
+    jack_ports: String,
+    channel_mode: ChannelMode,
+    visual_mode: VisualMode,
+    scope: Oscilloscope,
+    envelope: Envelope,
+    auto_gain: AutoGain,
+    smoothing: SmoothingConfig,
+    /// Waveform window eased between ticks.
+    smoothed_window: Smoothed,
+    spectrum_peaks: PeakHold,
+    waveform_peaks: PeakHold,
+    waveform_style: WaveformStyle,
+    /// Hex colors as typed, applied to the style once they parse.
+    color_input: String,
+    gradient_input: String,
+    spectrum: SpectrumAnalyzer,
+    spectrogram: SpectrogramHistory,
+    radial: RadialConfig,
+    goniometer: GoniometerHistory,
+    /// Current rotation of the radial visual in radians.
+    rotation: f32,
+    last_tick: Instant,
+    /// Samples written to the source's ring as of the last redraw.
+    last_written: usize,
+    target_fps: u32,
+    visual_cache: Cache,
+    logo_path: String,
+    logo: Option<image::Handle>,
+    error: Option<CaptureError>,
+    background_path: Option<PathBuf>,
+    /// Background path as typed, applied once it points at a file.
+    background_input: String,
     background_image: Option<image::Handle>,
+    background_fit: BackgroundFit,
+    background_opacity: f32,
+    /// Background of the visualizer page, keyed out in OBS.
+    chroma_color: Color,
+    chroma_input: String,
+    window: WindowGeometry,
+    /// The window geometry isn't tracked in fullscreen, so the saved size stays usable.
+    fullscreen: bool,
+    /// Last device picked from the modal, kept while other sources play.
+    last_device: Option<SavedDevice>,
+    /// Saved device to open as soon as source events can be delivered.
+    autostart: Option<DeviceSelection>,
+    config_path: Option<PathBuf>,
+    saved_config: Config,
+    /// What the app started with, command line options included.
+    launch_config: Config,
+    /// Config that differs from the saved one, and when it last changed.
+    pending_config: Option<(Config, Instant)>,
 }
 
 impl Default for App {
     fn default() -> Self {
-        let (tx, rx) = mpsc::channel();
-        let mut bg_path = env::current_dir().unwrap();
-        bg_path.push("bg.png");
-
         App {
             theme: Theme::Dark,
             show_output_modal: false,
-            output_device_names: Vec::new(),
+            output_devices: Vec::new(),
+            monitors: Vec::new(),
+            expanded_device: None,
+            buffer_choice: BufferChoice::default(),
             output_scrollable: ScrollableData {
                 width: 10,
                 margin: 0,
@@ -130,37 +286,123 @@ impl Default for App {
                 current_scroll_offset: scrollable::RelativeOffset::START,
             },
             page: Page::Main,
-            host: cpal::default_host(),
-            output_stream: None,
-            output_sender: tx,
-            output_reciever: Arc::new(rx),
-            background_image: if bg_path.try_exists().expect("path exist check failed") {
-                Some(image::Handle::from_path(bg_path))
-            } else {
-                None
-            },
+            source: None,
+            source_events: None,
+            source_generation: 0,
+            selected_device: None,
+            lost_device: None,
+            retrying: false,
+            audio_file_path: String::new(),
+            generator: GeneratorConfig::default(),
+            seed_input: GeneratorConfig::default().seed.to_string(),
+            #[cfg(feature = "jack")]
+            jack: source::JackConfig::default(),
+            #[cfg(feature = "jack")]
+            jack_ports: source::JackConfig::default().ports.join(", "),
+            channel_mode: ChannelMode::default(),
+            visual_mode: VisualMode::default(),
+            scope: Oscilloscope::new(TriggerConfig::default()),
+            envelope: Envelope::default(),
+            auto_gain: AutoGain::new(GainConfig::default()),
+            smoothing: SmoothingConfig::default(),
+            smoothed_window: Smoothed::default(),
+            spectrum_peaks: PeakHold::default(),
+            waveform_peaks: PeakHold::default(),
+            waveform_style: WaveformStyle::default(),
+            color_input: waveform_style::to_hex(WaveformStyle::default().color),
+            gradient_input: waveform_style::to_hex(RIGHT_GRADIENT_DEFAULT),
+            spectrum: SpectrumAnalyzer::new(SpectrumConfig::default()),
+            spectrogram: SpectrogramHistory::default(),
+            radial: RadialConfig::default(),
+            goniometer: GoniometerHistory::default(),
+            rotation: 0.,
+            last_tick: Instant::now(),
+            last_written: 0,
+            target_fps: DEFAULT_FPS,
+            visual_cache: Cache::new(),
+            logo_path: String::new(),
+            logo: None,
+            error: None,
+            background_path: None,
+            background_input: String::new(),
+            background_image: None,
+            background_fit: BackgroundFit::default(),
+            background_opacity: Config::default().background_opacity,
+            chroma_color: Config::default().chroma_color,
+            chroma_input: waveform_style::to_hex(Config::default().chroma_color),
+            window: WindowGeometry::default(),
+            fullscreen: false,
+            last_device: None,
+            autostart: None,
+            config_path: None,
+            saved_config: Config::default(),
+            launch_config: Config::default(),
+            pending_config: None,
         }
     }
 }
 
-fn err_fn(err: StreamError) {
-    eprintln!("an error occurred on stream: {}", err);
-}
-
-fn input_data_fn(data: &[f32], _: &cpal::InputCallbackInfo, tx: mpsc::Sender<Vec<f32>>) {
-    let output_data = data.iter().map(|sample| *sample).collect::<Vec<f32>>();
-
-    tx.send(output_data).unwrap();
-}
-
 impl Application for App {
     type Executor = executor::Default;
-    type Flags = ();
+    type Flags = Flags;
     type Message = Message;
     type Theme = Theme;
 
-    fn new(_flags: ()) -> (Self, Command<Self::Message>) {
-        (App::default(), Command::none())
+    fn new(flags: Flags) -> (Self, Command<Self::Message>) {
+        let Flags {
+            config: file_config,
+            launch: config,
+            config_path,
+            device,
+            fullscreen,
+        } = flags;
+
+        let mut app = App {
+            visual_mode: config.visual_mode,
+            waveform_style: config.waveform_style,
+            color_input: waveform_style::to_hex(config.waveform_style.color),
+            chroma_color: config.chroma_color,
+            chroma_input: waveform_style::to_hex(config.chroma_color),
+            background_fit: config.background_fit,
+            background_opacity: config.background_opacity,
+            generator: config.generator,
+            seed_input: config.generator.seed.to_string(),
+            window: config.window,
+            fullscreen,
+            last_device: config.device.clone(),
+            autostart: device.or_else(|| {
+                config
+                    .device
+                    .as_ref()
+                    .and_then(SavedDevice::selection)
+                    .filter(|x| source::device_exists(&x.id))
+            }),
+            config_path,
+            ..App::default()
+        };
+
+        if let Some(gradient) = config.waveform_style.gradient {
+            app.gradient_input = waveform_style::to_hex(gradient);
+        }
+
+        if let Some(ref path) = config.background {
+            if !path.is_file() {
+                eprintln!("background image {} not found", path.display());
+            }
+
+            app.set_background(path.display().to_string());
+        }
+
+        app.saved_config = file_config;
+        app.launch_config = app.current_config();
+
+        let command = if fullscreen {
+            window::change_mode(window::Mode::Fullscreen)
+        } else {
+            Command::none()
+        };
+
+        (app, command)
     }
 
     fn title(&self) -> String {
@@ -169,20 +411,62 @@ impl Application for App {
 
     fn subscription(&self) -> iced_native::Subscription<Self::Message> {
         let events = subscription::events().map(Message::Event);
-        let ticks = iced::time::every(std::time::Duration::from_millis(10)).map(|_| Message::Tick);
 
-        Subscription::batch(vec![events, ticks])
+        let mut subscriptions = vec![events, source_events()];
+
+        // Nothing animates on the main page, so only redraw while a visual is showing.
+        if matches!(self.page, Page::Visualizer) {
+            subscriptions.push(
+                iced::time::every(Duration::from_secs_f32(1. / self.target_fps as f32))
+                    .map(|_| Message::Tick),
+            );
+        }
+
+        if self.lost_device.is_some() {
+            subscriptions
+                .push(iced::time::every(Duration::from_secs(1)).map(|_| Message::RetryDevice));
+        }
+
+        if self.pending_config.is_some() {
+            subscriptions.push(iced::time::every(CONFIG_SAVE_DELAY).map(|_| Message::SaveConfig));
+        }
+
+        Subscription::batch(subscriptions)
     }
 
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
-        match message {
+        // Only these change how the cached visual looks. Ticks decide for themselves, switching
+        // sources resets the visuals and the cache already redraws on resize.
+        let restyled = match message {
+            Message::ChannelModeChanged(_)
+            | Message::VisualModeChanged(_)
+            | Message::TriggerConfigChanged(_)
+            | Message::EnvelopeChanged(_)
+            | Message::SmoothingConfigChanged(_)
+            | Message::GainConfigChanged(_)
+            | Message::WaveformStyleChanged(_)
+            | Message::WaveformColorChanged(_)
+            | Message::WaveformGradientChanged(_)
+            | Message::ColormapChanged(_)
+            | Message::RadialConfigChanged(_)
+            | Message::SpectrumConfigChanged(_) => true,
+            Message::Event(ref event) => {
+                matches!(event, Event::Keyboard(keyboard::Event::KeyPressed { .. }))
+            }
+            _ => false,
+        };
+
+        if restyled {
+            self.visual_cache.clear();
+        }
+
+        let tick = matches!(message, Message::Tick);
+
+        let command = match message {
             Message::ShowOutputModal => {
                 self.show_output_modal = true;
-
-                let output_devices = self.host.output_devices().unwrap();
-                self.output_device_names = output_devices
-                    .map(|device| device.name().unwrap())
-                    .collect::<Vec<String>>();
+                self.output_devices = source::list_devices();
+                self.monitors = source::list_monitors();
 
                 Command::none()
             }
@@ -190,54 +474,391 @@ impl Application for App {
                 self.hide_modal();
                 Command::none()
             }
-            Message::Tick => Command::none(),
+            Message::Tick => {
+                let now = Instant::now();
+                let elapsed = now.duration_since(self.last_tick).as_secs_f32();
+                self.last_tick = now;
+
+                let rotating =
+                    self.visual_mode == VisualMode::Radial && self.radial.rotation_speed != 0.;
+
+                if rotating {
+                    self.rotation = (self.rotation
+                        + self.radial.rotation_speed.to_radians() * elapsed)
+                        .rem_euclid(TAU);
+                }
+
+                if let (Page::Visualizer, Some(ref source)) = (&self.page, &self.source) {
+                    let written = source.samples().written();
+                    let fresh = written != self.last_written;
+                    self.last_written = written;
+
+                    // Snapshots and FFTs are only redone for new audio.
+                    if fresh {
+                        if self.shows_waveform() {
+                            self.scope.update(
+                                &source.samples(),
+                                source.channels(),
+                                source.sample_rate(),
+                                self.envelope,
+                            );
+                        }
+
+                        if self.uses_spectrum() {
+                            self.spectrum.update(
+                                &source.samples(),
+                                source.channels(),
+                                source.sample_rate(),
+                            );
+                        }
+
+                        if self.visual_mode == VisualMode::Goniometer {
+                            self.goniometer.update(&source.samples(), source.channels());
+                        }
+
+                        if self.visual_mode == VisualMode::Spectrogram {
+                            self.spectrogram
+                                .push(self.spectrum.bands(SPECTROGRAM_BANDS));
+                        }
+                    }
+
+                    // Eased and held values keep moving while the input is quiet, until they
+                    // settle.
+                    let mut moving = false;
+
+                    if self.shows_waveform() {
+                        moving |= self.auto_gain.update(self.scope.window(), elapsed);
+                        moving |= self.smoothed_window.update(
+                            self.scope.window(),
+                            self.smoothing.waveform_ms,
+                            elapsed,
+                        );
+                    }
+
+                    if self.visual_mode == VisualMode::Waveform
+                        && self.waveform_style.bars
+                        && self.smoothing.peak_hold
+                    {
+                        let levels = self
+                            .smoothed_window
+                            .values()
+                            .iter()
+                            .map(|x| x.abs())
+                            .collect::<Vec<_>>();
+
+                        moving |= self
+                            .waveform_peaks
+                            .update(&levels, &self.smoothing, elapsed);
+                    }
+
+                    if self.holds_peaks() && self.uses_spectrum() && self.smoothing.peak_hold {
+                        moving |= self.spectrum_peaks.update(
+                            &self.spectrum.bands(self.spectrum.config().bands),
+                            &self.smoothing,
+                            elapsed,
+                        );
+                    }
+
+                    if fresh || rotating || moving {
+                        self.visual_cache.clear();
+                    }
+                }
+
+                Command::none()
+            }
             Message::SelectedDevice(device) => {
                 self.hide_modal();
 
-                if let Some(ref output_stream) = self.output_stream {
-                    output_stream.pause().unwrap();
+                match DeviceSource::new(&device, self.next_source_events())
+                    .and_then(|source| self.use_source(Box::new(source)))
+                {
+                    Ok(()) => {
+                        self.last_device = Some(SavedDevice::from(&device));
+                        self.selected_device = Some(device);
+                    }
+                    Err(err) => self.show_error(err),
+                }
 
-                    let (tx, rx) = mpsc::channel();
-                    self.output_sender = tx;
-                    self.output_reciever = Arc::new(rx);
+                Command::none()
+            }
+            Message::ToggleDeviceConfigs(id) => {
+                if self.expanded_device.as_ref().map(|x| &x.0) == Some(&id) {
+                    self.expanded_device = None;
+                } else {
+                    match source::supported_configs(&id) {
+                        Ok(configs) => self.expanded_device = Some((id, configs)),
+                        Err(err) => self.show_error(err),
+                    }
                 }
 
-                let device = self
-                    .host
-                    .output_devices()
-                    .unwrap()
-                    .find(|x| x.name().map(|y| y == device).unwrap_or(false))
-                    .expect("failed to find input device {device}");
+                Command::none()
+            }
+            Message::BufferChoiceChanged(buffer_choice) => {
+                self.buffer_choice = buffer_choice;
+                Command::none()
+            }
+            Message::AudioFilePathChanged(path) => {
+                self.audio_file_path = path;
+                Command::none()
+            }
+            Message::SelectedAudioFile => {
+                match FileSource::open(&self.audio_file_path, self.next_source_events())
+                    .and_then(|source| self.use_source(Box::new(source)))
+                {
+                    Ok(()) => self.hide_modal(),
+                    Err(err) => self.show_error(err),
+                }
 
-                let config: cpal::StreamConfig = device.default_input_config().unwrap().into();
+                Command::none()
+            }
+            Message::GeneratorConfigChanged(config) => {
+                self.generator = config;
+                Command::none()
+            }
+            Message::GeneratorSeedChanged(input) => {
+                if let Ok(seed) = input.trim().parse() {
+                    self.generator.seed = seed;
+                }
 
-                let tx = self.output_sender.clone();
+                self.seed_input = input;
+                Command::none()
+            }
+            Message::SelectedSignal(signal) => {
+                self.hide_modal();
+
+                let source = GeneratorSource::new(GeneratorConfig {
+                    signal,
+                    ..self.generator
+                });
 
-                self.output_stream = Some(
-                    device
-                        .build_input_stream(
-                            &config,
-                            move |data: &[f32], cb_info: &cpal::InputCallbackInfo| {
-                                let tx = tx.clone();
+                if let Err(err) = self.use_source(Box::new(source)) {
+                    self.show_error(err);
+                }
 
-                                input_data_fn(data, cb_info, tx);
-                            },
-                            err_fn,
-                            None,
+                Command::none()
+            }
+            #[cfg(feature = "jack")]
+            Message::JackClientNameChanged(client_name) => {
+                self.jack.client_name = client_name;
+                Command::none()
+            }
+            #[cfg(feature = "jack")]
+            Message::JackPortsChanged(ports) => {
+                self.jack_ports = ports;
+                Command::none()
+            }
+            #[cfg(feature = "jack")]
+            Message::SelectedJack => {
+                self.hide_modal();
+
+                self.jack.ports = self
+                    .jack_ports
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|x| !x.is_empty())
+                    .map(str::to_string)
+                    .collect();
+
+                if let Err(err) = source::JackSource::new(&self.jack, self.next_source_events())
+                    .and_then(|source| self.use_source(Box::new(source)))
+                {
+                    self.show_error(err);
+                }
+
+                Command::none()
+            }
+            Message::ChannelModeChanged(channel_mode) => {
+                self.channel_mode = channel_mode;
+                Command::none()
+            }
+            Message::TargetFpsChanged(target_fps) => {
+                self.target_fps = target_fps;
+                Command::none()
+            }
+            Message::VisualModeChanged(visual_mode) => {
+                self.visual_mode = visual_mode;
+                Command::none()
+            }
+            Message::TriggerConfigChanged(config) => {
+                self.scope.config = config;
+                Command::none()
+            }
+            Message::WaveformStyleChanged(style) => {
+                self.waveform_style = style;
+                Command::none()
+            }
+            Message::WaveformColorChanged(input) => {
+                if let Some(color) = waveform_style::parse_hex(&input) {
+                    self.waveform_style.color = color;
+                }
+
+                self.color_input = input;
+                Command::none()
+            }
+            Message::ChromaColorChanged(input) => {
+                if let Some(color) = waveform_style::parse_hex(&input) {
+                    self.chroma_color = color;
+                }
+
+                self.chroma_input = input;
+                Command::none()
+            }
+            Message::WaveformGradientChanged(input) => {
+                if let (Some(color), Some(_)) = (
+                    waveform_style::parse_hex(&input),
+                    self.waveform_style.gradient,
+                ) {
+                    self.waveform_style.gradient = Some(color);
+                }
+
+                self.gradient_input = input;
+                Command::none()
+            }
+            Message::GainConfigChanged(config) => {
+                self.auto_gain.config = config;
+                Command::none()
+            }
+            Message::SmoothingConfigChanged(config) => {
+                if !config.peak_hold {
+                    self.spectrum_peaks.clear();
+                    self.waveform_peaks.clear();
+                }
+
+                self.smoothing = config;
+                Command::none()
+            }
+            Message::EnvelopeChanged(envelope) => {
+                self.envelope = envelope;
+                Command::none()
+            }
+            Message::ColormapChanged(colormap) => {
+                self.spectrogram.set_colormap(colormap);
+                Command::none()
+            }
+            Message::RadialConfigChanged(config) => {
+                self.radial = config;
+                Command::none()
+            }
+            Message::LogoPathChanged(path) => {
+                self.logo = Some(Path::new(&path))
+                    .filter(|x| x.is_file())
+                    .map(image::Handle::from_path);
+                self.logo_path = path;
+                Command::none()
+            }
+            Message::BackgroundPathChanged(path) => {
+                self.set_background(path);
+                Command::none()
+            }
+            Message::PickBackground => {
+                // Cancelling the dialog keeps whatever was typed.
+                let current = self.background_input.clone();
+
+                Command::perform(
+                    rfd::AsyncFileDialog::new()
+                        .set_title("Background Image")
+                        .add_filter("Images", &IMAGE_EXTENSIONS)
+                        .pick_file(),
+                    move |file| {
+                        Message::BackgroundPathChanged(
+                            file.map_or(current, |x| x.path().display().to_string()),
                         )
-                        .unwrap(),
-                );
+                    },
+                )
+            }
+            Message::BackgroundFitChanged(fit) => {
+                self.background_fit = fit;
+                Command::none()
+            }
+            Message::BackgroundOpacityChanged(opacity) => {
+                self.background_opacity = opacity;
+                Command::none()
+            }
+            Message::SpectrumConfigChanged(config) => {
+                self.spectrum.set_config(config);
+                Command::none()
+            }
+            Message::DismissError => {
+                self.error = None;
+                Command::none()
+            }
+            Message::SourceEventsReady(source_events) => {
+                self.source_events = Some(source_events);
 
-                if let Some(ref output_stream) = self.output_stream {
-                    output_stream.play().unwrap();
+                // Opened only now so the saved device can report a lost stream like any other.
+                match self.autostart.take() {
+                    Some(device) => {
+                        // Opening a device on launch isn't picking it, so the saved one stays.
+                        let last_device = self.last_device.clone();
+                        let command = self.update(Message::SelectedDevice(device));
+                        self.last_device = last_device;
+
+                        command
+                    }
+                    None => Command::none(),
                 }
+            }
+            Message::SourceEvent(generation, SourceEvent::StreamLost(reason)) => {
+                // A source that was replaced since may still have had an event on its way.
+                if generation == self.source_generation && self.lost_device.is_none() {
+                    // Dropping the dead stream releases the device so it can be reopened.
+                    self.source = None;
 
-                self.page = Page::Waveform;
+                    match self.selected_device {
+                        Some(ref device) => {
+                            eprintln!("lost {}: {reason}", device.id);
+                            self.lost_device = Some(device.clone());
+                        }
+                        None => self.show_error(CaptureError::StreamLost(reason)),
+                    }
+                }
 
-                self.theme = Theme::custom(theme::Palette {
-                    background: Color::from_rgb(0., 1., 0.),
-                    ..Theme::Light.palette()
-                });
+                Command::none()
+            }
+            Message::SaveConfig => {
+                if let Some((_, changed)) = self.pending_config {
+                    if changed.elapsed() >= CONFIG_SAVE_DELAY {
+                        self.save_config();
+                    }
+                }
+
+                Command::none()
+            }
+            Message::RetryDevice => match self.lost_device {
+                Some(ref device) if !self.retrying => {
+                    self.retrying = true;
+                    let id = device.id.clone();
+
+                    // Listing devices can take a while, e.g. monitors run `pactl`, so it's done off
+                    // the UI thread.
+                    Command::perform(
+                        async move {
+                            let found = source::device_exists(&id);
+                            (id, found)
+                        },
+                        |(id, found)| Message::DeviceChecked(id, found),
+                    )
+                }
+                _ => Command::none(),
+            },
+            Message::DeviceChecked(id, found) => {
+                self.retrying = false;
+
+                match self.lost_device.clone() {
+                    Some(device) if found && device.id == id => {
+                        let source = DeviceSource::new(&device, self.next_source_events())
+                            .and_then(|mut source| source.start().map(|_| source));
+
+                        if let Ok(source) = source {
+                            // The new stream counts its samples from zero again.
+                            self.source = Some(Box::new(source));
+                            self.source_generation += 1;
+                            self.lost_device = None;
+                            self.reset_visuals();
+                        }
+                    }
+                    _ => {}
+                }
 
                 Command::none()
             }
@@ -259,47 +880,101 @@ impl Application for App {
                     match self.page {
                         Page::Main => {
                             self.hide_modal();
+                            self.error = None;
 
                             self.theme = Theme::Dark;
                         }
-                        Page::Waveform => {
+                        Page::Visualizer => {
                             self.page = Page::Main;
+                            // Stop reconnecting, the user gave up on the device.
+                            self.lost_device = None;
 
-                            self.theme = Theme::custom(theme::Palette {
-                                background: Color::from_rgb(0., 1., 0.),
-                                ..Theme::Light.palette()
-                            });
+                            self.theme = self.chroma_theme();
                         }
                     }
 
                     Command::none()
                 }
+                Event::Keyboard(keyboard::Event::KeyPressed { key_code, .. })
+                    if matches!(self.page, Page::Visualizer) =>
+                {
+                    match key_code {
+                        keyboard::KeyCode::C => self.channel_mode = self.channel_mode.next(),
+                        keyboard::KeyCode::V => self.visual_mode = self.visual_mode.next(),
+                        _ => {}
+                    }
+
+                    if let Some(playback) = self.source.as_mut().and_then(|x| x.playback()) {
+                        match key_code {
+                            keyboard::KeyCode::Space => playback.toggle_playing(),
+                            keyboard::KeyCode::L => playback.toggle_looping(),
+                            keyboard::KeyCode::Left => playback.seek_by(-5.),
+                            keyboard::KeyCode::Right => playback.seek_by(5.),
+                            _ => {}
+                        }
+                    }
+
+                    Command::none()
+                }
+                Event::Window(window::Event::CloseRequested) => {
+                    self.save_config();
+                    window::close()
+                }
+                Event::Window(_) if self.fullscreen => Command::none(),
+                Event::Window(window::Event::Resized { width, height }) => {
+                    self.window.width = width;
+                    self.window.height = height;
+                    Command::none()
+                }
+                Event::Window(window::Event::Moved { x, y }) => {
+                    self.window.position = Some((x, y));
+                    Command::none()
+                }
                 _ => Command::none(),
             },
+        };
+
+        if !tick {
+            let config = self.config();
+
+            if config == self.saved_config {
+                self.pending_config = None;
+            } else if self.pending_config.as_ref().map(|x| &x.0) != Some(&config) {
+                self.pending_config = Some((config, Instant::now()));
+            }
         }
+
+        command
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         match self.page {
             Page::Main => {
                 let content = container(
                     column![
                         row![
-                            text("Top Left"),
+                            self.settings(),
                             horizontal_space(Length::Fill),
                             text("Top Right"),
                         ]
                         .align_items(Alignment::Start)
                         .height(Length::Fill),
                         container(
-                            button(text("Select Output Device")).on_press(Message::ShowOutputModal)
+                            button(text("Select Audio Source")).on_press(Message::ShowOutputModal)
                         )
                         .center_x()
                         .center_y()
                         .width(Length::Fill)
                         .height(Length::Fill),
                         row![
-                            text("Bottom Left"),
+                            text(match self.source {
+                                Some(ref source) => format!(
+                                    "{} Hz, {} ch",
+                                    source.sample_rate(),
+                                    source.channels()
+                                ),
+                                None => "Bottom Left".to_string(),
+                            }),
                             horizontal_space(Length::Fill),
                             text("Bottom Right"),
                         ]
@@ -312,28 +987,30 @@ impl Application for App {
                 .width(Length::Fill)
                 .height(Length::Fill);
 
-                if self.show_output_modal {
-                    let mut output_devices_column =
-                        column![text("Output Devices").size(24), horizontal_rule(10)];
-
-                    for output_device_name in &self.output_device_names {
-                        if self.output_device_names.first().unwrap() != output_device_name {
-                            output_devices_column = output_devices_column.push(vertical_space(10));
-                        }
-
-                        output_devices_column = output_devices_column.push(
-                            button(text(output_device_name))
-                                .width(Length::Fill)
-                                .on_press(Message::SelectedDevice(output_device_name.clone())),
-                        );
-                    }
+                if let Some(ref error) = self.error {
+                    let modal = container(column![
+                        text("Capture Error").size(24),
+                        horizontal_rule(10),
+                        text(error),
+                        vertical_space(10),
+                        button(text("OK"))
+                            .width(Length::Fill)
+                            .on_press(Message::DismissError),
+                    ])
+                    .width(300)
+                    .padding(10)
+                    .style(theme::Container::Box);
 
+                    Modal::new(content, modal)
+                        .on_blur(Message::DismissError)
+                        .into()
+                } else if self.show_output_modal {
                     let modal = container(
-                        scrollable(output_devices_column)
+                        scrollable(self.device_picker())
                             .width(Length::Fill)
                             .id(OUTPUT_SCROLLABLE_ID.clone()),
                     )
-                    .width(300)
+                    .width(400)
                     .padding(10)
                     .style(theme::Container::Box);
 
@@ -344,17 +1021,93 @@ impl Application for App {
                     content.into()
                 }
             }
-            Page::Waveform => {
-                let rx = Arc::clone(&self.output_reciever);
+            Page::Visualizer => {
+                if let Some(ref device) = self.lost_device {
+                    return container(
+                        text(format!("Device lost: {}\nReconnecting...", device.id.name)).size(32),
+                    )
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .center_x()
+                    .center_y()
+                    .into();
+                }
+
+                let channels = self.source.as_ref().map_or(1, |x| x.channels());
 
-                container(
-                    Canvas::new(Waveform { rx })
+                let visual: Element<'_, Message> = match self.visual_mode {
+                    VisualMode::Waveform => self.cached(Waveform {
+                        data: self.smoothed_window.values(),
+                        channels,
+                        channel_mode: self.channel_mode,
+                        envelope: self.envelope,
+                        style: self.waveform_style,
+                        gain: self.auto_gain.mapping(),
+                        peaks: self.waveform_peaks.peaks(),
+                    }),
+                    VisualMode::Spectrum => {
+                        let config = self.spectrum.config();
+
+                        self.cached(Spectrum {
+                            bands: self.spectrum.bands(config.bands),
+                            peaks: self.spectrum_peaks.peaks().to_vec(),
+                            style: config.style,
+                        })
+                    }
+                    VisualMode::Spectrogram => image(self.spectrogram.image())
                         .width(Length::Fill)
-                        .height(Length::Fill),
-                )
-                .width(Length::Fill)
-                .height(Length::Fill)
-                .into()
+                        .height(Length::Fill)
+                        .content_fit(ContentFit::Fill)
+                        .into(),
+                    VisualMode::Goniometer => self.cached(Goniometer {
+                        history: &self.goniometer,
+                    }),
+                    VisualMode::Radial => {
+                        let data = match self.radial.source {
+                            RadialSource::Waveform => RadialData::Waveform {
+                                data: self.smoothed_window.values().to_vec(),
+                                channels,
+                                gain: self.auto_gain.mapping(),
+                            },
+                            RadialSource::Spectrum => RadialData::Spectrum {
+                                bands: self.spectrum.bands(self.spectrum.config().bands),
+                                peaks: self.spectrum_peaks.peaks().to_vec(),
+                            },
+                        };
+                        let ring = self.cached(Radial {
+                            data,
+                            config: self.radial,
+                            rotation: self.rotation,
+                        });
+
+                        match self.logo {
+                            Some(ref handle) => Stack::new(Logo {
+                                handle: handle.clone(),
+                                config: self.radial,
+                            })
+                            .push(ring)
+                            .into(),
+                            None => ring,
+                        }
+                    }
+                };
+
+                let visual = match self.background_image {
+                    Some(ref handle) => Stack::new(Background {
+                        handle: handle.clone(),
+                        fit: self.background_fit,
+                        opacity: self.background_opacity,
+                        backdrop: self.chroma_color,
+                    })
+                    .push(visual)
+                    .into(),
+                    None => visual,
+                };
+
+                container(visual)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .into()
             }
         }
     }
@@ -365,11 +1118,917 @@ impl Application for App {
 }
 
 impl App {
+    fn cached<'a>(&'a self, visual: impl Visual + 'a) -> Element<'a, Message> {
+        Canvas::new(Cached {
+            cache: &self.visual_cache,
+            visual,
+        })
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
     fn hide_modal(&mut self) {
         self.show_output_modal = false;
+        self.expanded_device = None;
+    }
+
+    /// Whether the current visual is drawn from the oscilloscope's window.
+    fn shows_waveform(&self) -> bool {
+        match self.visual_mode {
+            VisualMode::Waveform => true,
+            VisualMode::Radial => self.radial.source == RadialSource::Waveform,
+            VisualMode::Spectrum | VisualMode::Spectrogram | VisualMode::Goniometer => false,
+        }
+    }
+
+    /// Whether the current visual is drawn from the spectrum analyzer.
+    fn uses_spectrum(&self) -> bool {
+        match self.visual_mode {
+            VisualMode::Waveform | VisualMode::Goniometer => false,
+            VisualMode::Spectrum | VisualMode::Spectrogram => true,
+            VisualMode::Radial => self.radial.source == RadialSource::Spectrum,
+        }
+    }
+
+    /// Whether the current visual has bars whose peaks can be held.
+    fn holds_peaks(&self) -> bool {
+        match self.visual_mode {
+            VisualMode::Waveform => self.waveform_style.bars,
+            VisualMode::Spectrum => true,
+            VisualMode::Radial => self.radial.source == RadialSource::Spectrum,
+            VisualMode::Spectrogram | VisualMode::Goniometer => false,
+        }
+    }
+
+    fn gain_settings(&self) -> Element<'_, Message> {
+        let config = self.auto_gain.config;
+
+        let mut gain = row![
+            text(format!("Gain {:+.0} dB", config.gain_db)),
+            slider(-20.0..=40.0, config.gain_db, move |gain_db| {
+                Message::GainConfigChanged(GainConfig { gain_db, ..config })
+            })
+            .step(1.)
+            .width(100),
+            checkbox("Auto Gain", config.auto, move |auto| {
+                Message::GainConfigChanged(GainConfig { auto, ..config })
+            }),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        if config.auto {
+            gain = gain
+                .push(text(format!("Attack {:.0} ms", config.attack_ms)))
+                .push(
+                    slider(1.0..=500.0, config.attack_ms, move |attack_ms| {
+                        Message::GainConfigChanged(GainConfig {
+                            attack_ms,
+                            ..config
+                        })
+                    })
+                    .step(1.)
+                    .width(80),
+                )
+                .push(text(format!("Release {:.0} ms", config.release_ms)))
+                .push(
+                    slider(50.0..=5000.0, config.release_ms, move |release_ms| {
+                        Message::GainConfigChanged(GainConfig {
+                            release_ms,
+                            ..config
+                        })
+                    })
+                    .step(10.)
+                    .width(80),
+                );
+        }
+
+        column![
+            gain,
+            row![
+                text("Scale"),
+                pick_list(&AmplitudeScale::ALL[..], Some(config.scale), move |scale| {
+                    Message::GainConfigChanged(GainConfig { scale, ..config })
+                }),
+                checkbox("Soft Clip", config.soft_clip, move |soft_clip| {
+                    Message::GainConfigChanged(GainConfig {
+                        soft_clip,
+                        ..config
+                    })
+                }),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        ]
+        .spacing(5)
+        .into()
+    }
+
+    fn waveform_style_settings(&self) -> Element<'_, Message> {
+        let style = self.waveform_style;
+        let gradient_end =
+            waveform_style::parse_hex(&self.gradient_input).unwrap_or(RIGHT_GRADIENT_DEFAULT);
+
+        let mut colors = row![
+            text("Color"),
+            text_input("#000000", &self.color_input)
+                .on_input(Message::WaveformColorChanged)
+                .width(90),
+            checkbox("Gradient", style.gradient.is_some(), move |enabled| {
+                Message::WaveformStyleChanged(WaveformStyle {
+                    gradient: enabled.then_some(gradient_end),
+                    ..style
+                })
+            }),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        if style.gradient.is_some() {
+            colors = colors.push(
+                text_input("#000000", &self.gradient_input)
+                    .on_input(Message::WaveformGradientChanged)
+                    .width(90),
+            );
+        }
+
+        column![
+            colors,
+            row![
+                text(format!("Width {:.1}", style.width)),
+                slider(0.5..=10.0, style.width, move |width| {
+                    Message::WaveformStyleChanged(WaveformStyle { width, ..style })
+                })
+                .step(0.5)
+                .width(100),
+                pick_list(&Cap::ALL[..], Some(style.cap), move |cap| {
+                    Message::WaveformStyleChanged(WaveformStyle { cap, ..style })
+                }),
+                pick_list(&Join::ALL[..], Some(style.join), move |join| {
+                    Message::WaveformStyleChanged(WaveformStyle { join, ..style })
+                }),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                checkbox("Fill", style.fill, move |fill| {
+                    Message::WaveformStyleChanged(WaveformStyle { fill, ..style })
+                }),
+                checkbox("Mirror", style.mirror, move |mirror| {
+                    Message::WaveformStyleChanged(WaveformStyle { mirror, ..style })
+                }),
+                checkbox("Bars", style.bars, move |bars| {
+                    Message::WaveformStyleChanged(WaveformStyle { bars, ..style })
+                }),
+                text(format!("Glow {}", style.glow)),
+                slider(0..=6, style.glow, move |glow| {
+                    Message::WaveformStyleChanged(WaveformStyle { glow, ..style })
+                })
+                .width(100),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        ]
+        .spacing(5)
+        .into()
+    }
+
+    fn settings(&self) -> Element<'_, Message> {
+        let mut settings = column![
+            row![
+                text("Visual"),
+                pick_list(
+                    &VisualMode::ALL[..],
+                    Some(self.visual_mode),
+                    Message::VisualModeChanged
+                ),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                text("Channels"),
+                pick_list(
+                    &ChannelMode::ALL[..],
+                    Some(self.channel_mode),
+                    Message::ChannelModeChanged
+                ),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                text(format!("Target {} FPS", self.target_fps)),
+                slider(10..=144, self.target_fps, Message::TargetFpsChanged).width(150),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                text("Chroma"),
+                text_input("#00ff00", &self.chroma_input)
+                    .on_input(Message::ChromaColorChanged)
+                    .width(90),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                text("Background"),
+                text_input("path/to/background.png", &self.background_input)
+                    .on_input(Message::BackgroundPathChanged)
+                    .width(250),
+                button(text("Browse")).on_press(Message::PickBackground),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        ]
+        .spacing(5);
+
+        if self.background_image.is_some() {
+            settings = settings.push(
+                row![
+                    pick_list(
+                        &BackgroundFit::ALL[..],
+                        Some(self.background_fit),
+                        Message::BackgroundFitChanged
+                    ),
+                    text(format!("Opacity {:.0}%", self.background_opacity * 100.)),
+                    slider(
+                        0.0..=1.0,
+                        self.background_opacity,
+                        Message::BackgroundOpacityChanged
+                    )
+                    .step(0.01)
+                    .width(100),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        if self.visual_mode == VisualMode::Waveform {
+            let config = self.scope.config;
+
+            settings = settings.push(
+                row![
+                    text("Trigger"),
+                    pick_list(&TriggerEdge::ALL[..], Some(config.edge), move |edge| {
+                        Message::TriggerConfigChanged(TriggerConfig { edge, ..config })
+                    }),
+                    text(format!("Window {:.0} ms", config.window_ms)),
+                    slider(5.0..=5000.0, config.window_ms, move |window_ms| {
+                        Message::TriggerConfigChanged(TriggerConfig {
+                            window_ms,
+                            ..config
+                        })
+                    })
+                    .step(1.)
+                    .width(100),
+                    pick_list(
+                        &Envelope::ALL[..],
+                        Some(self.envelope),
+                        Message::EnvelopeChanged
+                    ),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+
+            if config.edge != TriggerEdge::Off {
+                settings = settings.push(
+                    row![
+                        text(format!("Level {:+.2}", config.level)),
+                        slider(-1.0..=1.0, config.level, move |level| {
+                            Message::TriggerConfigChanged(TriggerConfig { level, ..config })
+                        })
+                        .step(0.01)
+                        .width(100),
+                        text(format!("Hysteresis {:.2}", config.hysteresis)),
+                        slider(0.0..=0.5, config.hysteresis, move |hysteresis| {
+                            Message::TriggerConfigChanged(TriggerConfig {
+                                hysteresis,
+                                ..config
+                            })
+                        })
+                        .step(0.01)
+                        .width(100),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center),
+                );
+                settings = settings.push(
+                    row![
+                        text(format!("Holdoff {:.0} ms", config.holdoff_ms)),
+                        slider(0.0..=200.0, config.holdoff_ms, move |holdoff_ms| {
+                            Message::TriggerConfigChanged(TriggerConfig {
+                                holdoff_ms,
+                                ..config
+                            })
+                        })
+                        .step(1.)
+                        .width(100),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center),
+                );
+            }
+
+            let smoothing = self.smoothing;
+
+            settings = settings.push(
+                row![
+                    text(if smoothing.waveform_ms > 0. {
+                        format!("Smoothing {:.0} ms", smoothing.waveform_ms)
+                    } else {
+                        "Smoothing Off".to_string()
+                    }),
+                    slider(0.0..=500.0, smoothing.waveform_ms, move |waveform_ms| {
+                        Message::SmoothingConfigChanged(SmoothingConfig {
+                            waveform_ms,
+                            ..smoothing
+                        })
+                    })
+                    .step(5.)
+                    .width(100),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+            settings = settings.push(self.gain_settings());
+            settings = settings.push(self.waveform_style_settings());
+        }
+
+        if self.visual_mode == VisualMode::Radial {
+            let config = self.radial;
+
+            settings = settings.push(
+                row![
+                    pick_list(&RadialSource::ALL[..], Some(config.source), move |source| {
+                        Message::RadialConfigChanged(RadialConfig { source, ..config })
+                    }),
+                    checkbox("Mirror", config.mirror, move |mirror| {
+                        Message::RadialConfigChanged(RadialConfig { mirror, ..config })
+                    }),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+            settings = settings.push(
+                row![
+                    text(format!("Radius {:.0}%", config.radius * 100.)),
+                    slider(0.1..=0.9, config.radius, move |radius| {
+                        Message::RadialConfigChanged(RadialConfig { radius, ..config })
+                    })
+                    .step(0.01)
+                    .width(150),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+            settings = settings.push(
+                row![
+                    text(format!("Rotation {:.0}°/s", config.rotation_speed)),
+                    slider(
+                        -180.0..=180.0,
+                        config.rotation_speed,
+                        move |rotation_speed| {
+                            Message::RadialConfigChanged(RadialConfig {
+                                rotation_speed,
+                                ..config
+                            })
+                        }
+                    )
+                    .step(1.)
+                    .width(150),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+            settings = settings.push(
+                row![
+                    text("Logo"),
+                    text_input("path/to/logo.png", &self.logo_path)
+                        .on_input(Message::LogoPathChanged)
+                        .width(250),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        if self.uses_spectrum() {
+            let config = self.spectrum.config();
+
+            let mut fft_row = row![
+                text("FFT Size"),
+                pick_list(&FFT_SIZES[..], Some(config.fft_size), move |fft_size| {
+                    Message::SpectrumConfigChanged(SpectrumConfig { fft_size, ..config })
+                }),
+                pick_list(&Window::ALL[..], Some(config.window), move |window| {
+                    Message::SpectrumConfigChanged(SpectrumConfig { window, ..config })
+                }),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center);
+
+            fft_row = match self.visual_mode {
+                VisualMode::Spectrogram => fft_row.push(pick_list(
+                    &Colormap::ALL[..],
+                    Some(self.spectrogram.colormap()),
+                    Message::ColormapChanged,
+                )),
+                VisualMode::Spectrum => fft_row.push(pick_list(
+                    &SpectrumStyle::ALL[..],
+                    Some(config.style),
+                    move |style| Message::SpectrumConfigChanged(SpectrumConfig { style, ..config }),
+                )),
+                _ => fft_row,
+            };
+
+            settings = settings.push(fft_row);
+            settings = settings.push(
+                row![
+                    text(format!("Smoothing {:.2}", config.smoothing)),
+                    slider(0.0..=0.99, config.smoothing, move |smoothing| {
+                        Message::SpectrumConfigChanged(SpectrumConfig {
+                            smoothing,
+                            ..config
+                        })
+                    })
+                    .step(0.01)
+                    .width(150),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+            settings = settings.push(
+                row![
+                    text(format!(
+                        "Range {:.0} to {:.0} dB",
+                        config.min_db, config.max_db
+                    )),
+                    slider(-140.0..=-20.0, config.min_db, move |min_db| {
+                        Message::SpectrumConfigChanged(SpectrumConfig { min_db, ..config })
+                    })
+                    .step(1.)
+                    .width(100),
+                    slider(-10.0..=20.0, config.max_db, move |max_db| {
+                        Message::SpectrumConfigChanged(SpectrumConfig { max_db, ..config })
+                    })
+                    .step(1.)
+                    .width(100),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        if self.holds_peaks() {
+            let smoothing = self.smoothing;
+
+            let mut peaks = row![checkbox(
+                "Peak Hold",
+                smoothing.peak_hold,
+                move |peak_hold| {
+                    Message::SmoothingConfigChanged(SmoothingConfig {
+                        peak_hold,
+                        ..smoothing
+                    })
+                }
+            )]
+            .spacing(10)
+            .align_items(Alignment::Center);
+
+            if smoothing.peak_hold {
+                peaks = peaks
+                    .push(text(format!("Hold {:.0} ms", smoothing.hold_ms)))
+                    .push(
+                        slider(0.0..=3000.0, smoothing.hold_ms, move |hold_ms| {
+                            Message::SmoothingConfigChanged(SmoothingConfig {
+                                hold_ms,
+                                ..smoothing
+                            })
+                        })
+                        .step(50.)
+                        .width(80),
+                    )
+                    .push(text(format!("Decay {:.2}/s", smoothing.decay)))
+                    .push(
+                        slider(0.05..=3.0, smoothing.decay, move |decay| {
+                            Message::SmoothingConfigChanged(SmoothingConfig { decay, ..smoothing })
+                        })
+                        .step(0.05)
+                        .width(80),
+                    );
+            }
+
+            settings = settings.push(peaks);
+        }
+
+        settings.into()
+    }
+
+    fn device_picker(&self) -> Element<'_, Message> {
+        let mut picker = column![];
+
+        if !self.monitors.is_empty() {
+            picker = picker
+                .push(text("Desktop Audio").size(24))
+                .push(horizontal_rule(10))
+                .push(self.device_list(&self.monitors))
+                .push(vertical_space(20));
+        }
+
+        for host in &self.output_devices {
+            picker = picker
+                .push(text(host.host.name()).size(24))
+                .push(horizontal_rule(10));
+
+            if let Some(ref err) = host.error {
+                picker = picker.push(text(format!("Unavailable: {err}")));
+            }
+
+            picker = picker
+                .push(text("Input Devices").size(18))
+                .push(self.device_list(&host.inputs))
+                .push(vertical_space(10))
+                .push(text("Output Devices").size(18))
+                .push(self.device_list(&host.outputs))
+                .push(vertical_space(20));
+        }
+
+        #[cfg(feature = "jack")]
+        {
+            picker = picker
+                .push(text("JACK").size(24))
+                .push(horizontal_rule(10))
+                .push(text("Client Name"))
+                .push(
+                    text_input("stream-intro", &self.jack.client_name)
+                        .on_input(Message::JackClientNameChanged),
+                )
+                .push(text("Input Ports"))
+                .push(
+                    text_input("in_l, in_r", &self.jack_ports)
+                        .on_input(Message::JackPortsChanged)
+                        .on_submit(Message::SelectedJack),
+                )
+                .push(vertical_space(10))
+                .push(
+                    button(text("Start JACK Client"))
+                        .width(Length::Fill)
+                        .on_press(Message::SelectedJack),
+                )
+                .push(vertical_space(20));
+        }
+
+        let config = self.generator;
+        let (sweep_start, sweep_end) = config.sweep_range;
+
+        picker = picker
+            .push(text("Audio File").size(24))
+            .push(horizontal_rule(10))
+            .push(
+                text_input("path/to/intro.flac", &self.audio_file_path)
+                    .on_input(Message::AudioFilePathChanged)
+                    .on_submit(Message::SelectedAudioFile),
+            )
+            .push(vertical_space(10))
+            .push(
+                button(text("Play File"))
+                    .width(Length::Fill)
+                    .on_press(Message::SelectedAudioFile),
+            )
+            .push(vertical_space(20))
+            .push(text("Test Signals").size(24))
+            .push(horizontal_rule(10))
+            .push(text(format!("Frequency: {:.0} Hz", config.frequency)))
+            .push(
+                slider(20.0..=2000.0, config.frequency, move |frequency| {
+                    Message::GeneratorConfigChanged(GeneratorConfig {
+                        frequency,
+                        ..config
+                    })
+                })
+                .step(1.),
+            )
+            .push(text(format!("Amplitude: {:.2}", config.amplitude)))
+            .push(
+                slider(0.0..=1.0, config.amplitude, move |amplitude| {
+                    Message::GeneratorConfigChanged(GeneratorConfig {
+                        amplitude,
+                        ..config
+                    })
+                })
+                .step(0.01),
+            )
+            .push(text(format!(
+                "Sweep: {:.0} Hz to {:.0} Hz over {:.0} s",
+                sweep_start, sweep_end, config.sweep_duration
+            )))
+            .push(
+                row![
+                    slider(20.0..=2000.0, sweep_start, move |sweep_start| {
+                        Message::GeneratorConfigChanged(GeneratorConfig {
+                            sweep_range: (sweep_start, sweep_end),
+                            ..config
+                        })
+                    })
+                    .step(1.),
+                    slider(200.0..=20000.0, sweep_end, move |sweep_end| {
+                        Message::GeneratorConfigChanged(GeneratorConfig {
+                            sweep_range: (sweep_start, sweep_end),
+                            ..config
+                        })
+                    })
+                    .step(10.),
+                    slider(1.0..=60.0, config.sweep_duration, move |sweep_duration| {
+                        Message::GeneratorConfigChanged(GeneratorConfig {
+                            sweep_duration,
+                            ..config
+                        })
+                    })
+                    .step(1.),
+                ]
+                .spacing(10),
+            )
+            .push(text(format!("Click Track: {:.0} BPM", config.bpm)))
+            .push(
+                slider(30.0..=300.0, config.bpm, move |bpm| {
+                    Message::GeneratorConfigChanged(GeneratorConfig { bpm, ..config })
+                })
+                .step(1.),
+            )
+            .push(
+                row![
+                    text("Noise Seed"),
+                    text_input("seed", &self.seed_input).on_input(Message::GeneratorSeedChanged),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+
+        for signal in Signal::ALL {
+            picker = picker.push(vertical_space(10)).push(
+                button(text(signal))
+                    .width(Length::Fill)
+                    .on_press(Message::SelectedSignal(signal)),
+            );
+        }
+
+        picker.into()
     }
+
+    fn device_list(&self, devices: &[DeviceInfo]) -> Element<'_, Message> {
+        let mut list = column![].spacing(10);
+
+        if devices.is_empty() {
+            list = list.push(text("None found"));
+        }
+
+        for device in devices {
+            let label = if device.is_default {
+                format!("{} (default)", device.label)
+            } else {
+                device.label.clone()
+            };
+
+            let expanded = self
+                .expanded_device
+                .as_ref()
+                .filter(|(id, _)| *id == device.id);
+
+            list = list.push(
+                row![
+                    button(text(label))
+                        .width(Length::Fill)
+                        .on_press(Message::SelectedDevice(DeviceSelection {
+                            id: device.id.clone(),
+                            config: None,
+                        })),
+                    button(text(if expanded.is_some() { "-" } else { "+" }))
+                        .on_press(Message::ToggleDeviceConfigs(device.id.clone())),
+                ]
+                .spacing(5),
+            );
+
+            if let Some((id, configs)) = expanded {
+                list = list.push(self.config_list(id, configs));
+            }
+        }
+
+        list.into()
+    }
+
+    fn config_list(
+        &self,
+        id: &DeviceId,
+        configs: &[SupportedStreamConfigRange],
+    ) -> Element<'_, Message> {
+        let mut list = column![pick_list(
+            source::buffer_sizes(configs),
+            Some(self.buffer_choice),
+            Message::BufferChoiceChanged
+        )]
+        .spacing(5)
+        .padding([0, 0, 0, 20]);
+
+        if configs.is_empty() {
+            list = list.push(text("No supported configs"));
+        }
+
+        for range in configs {
+            list = list.push(text(source::describe_range(range)));
+
+            for sample_rates in source::sample_rates(range).chunks(3) {
+                let mut rates = row![].spacing(5);
+
+                for &sample_rate in sample_rates {
+                    rates = rates.push(
+                        button(text(format!("{sample_rate} Hz")))
+                            .width(Length::Fill)
+                            .on_press(Message::SelectedDevice(DeviceSelection {
+                                id: id.clone(),
+                                config: Some(CaptureConfig {
+                                    channels: range.channels(),
+                                    sample_rate,
+                                    sample_format: range.sample_format(),
+                                    buffer_size: self.buffer_choice,
+                                }),
+                            })),
+                    );
+                }
+
+                list = list.push(rates);
+            }
+        }
+
+        list.into()
+    }
+
+    fn show_error(&mut self, error: CaptureError) {
+        self.hide_modal();
+        self.error = Some(error);
+        self.page = Page::Main;
+    }
+
+    /// Starts `source` and shows it, replacing whatever played before.
+    fn use_source(&mut self, mut source: Box<dyn AudioSource>) -> source::Result<()> {
+        if let Some(mut old_source) = self.source.take() {
+            if let Err(err) = old_source.stop() {
+                eprintln!("failed to stop previous source: {}", err);
+            }
+        }
+
+        self.selected_device = None;
+        self.lost_device = None;
+        self.source_generation += 1;
+
+        source.start()?;
+
+        self.source = Some(source);
+        self.reset_visuals();
+
+        self.page = Page::Visualizer;
+        // Ticks stopped while away, the first one back shouldn't step a long pause at once.
+        self.last_tick = Instant::now();
+
+        self.theme = self.chroma_theme();
+
+        Ok(())
+    }
+
+    /// Where the next source started should send its events.
+    fn next_source_events(&self) -> Option<EventSender> {
+        self.source_events
+            .clone()
+            .map(|x| EventSender::new(self.source_generation + 1, x))
+    }
+
+    /// Forgets everything the visuals remember about the previous source.
+    fn reset_visuals(&mut self) {
+        self.visual_cache.clear();
+        self.last_written = 0;
+        self.scope.clear();
+        self.auto_gain.reset();
+        self.smoothed_window.clear();
+        self.spectrum_peaks.clear();
+        self.waveform_peaks.clear();
+        self.spectrogram.clear();
+        self.goniometer.clear();
+    }
+
+    /// Shows the image at `path` behind the visual once it points at a file.
+    fn set_background(&mut self, path: String) {
+        self.background_path = Some(PathBuf::from(&path)).filter(|x| x.is_file());
+        self.background_image = self.background_path.clone().map(image::Handle::from_path);
+        self.background_input = path;
+    }
+
+    fn chroma_theme(&self) -> Theme {
+        Theme::custom(theme::Palette {
+            background: self.chroma_color,
+            ..Theme::Light.palette()
+        })
+    }
+
+    /// The config to save: the user's changes on top of the saved config, leaving out options
+    /// that only applied to this launch.
+    fn config(&self) -> Config {
+        self.saved_config
+            .with_changes(&self.launch_config, &self.current_config())
+    }
+
+    fn current_config(&self) -> Config {
+        Config {
+            device: self.last_device.clone(),
+            visual_mode: self.visual_mode,
+            waveform_style: self.waveform_style,
+            chroma_color: self.chroma_color,
+            background: self.background_path.clone(),
+            background_fit: self.background_fit,
+            background_opacity: self.background_opacity,
+            generator: self.generator,
+            window: self.window,
+        }
+    }
+
+    /// Writes the config out if it differs from what was last saved.
+    fn save_config(&mut self) {
+        self.pending_config = None;
+
+        let config = self.config();
+
+        if config == self.saved_config {
+            return;
+        }
+
+        if let Some(ref path) = self.config_path {
+            if let Err(err) = config.save(path) {
+                eprintln!("failed to save config {}: {}", path.display(), err);
+            }
+        }
+
+        self.saved_config = config;
+    }
+}
+
+/// Hands the app a sender for [`SourceEvent`]s and turns everything sent on it into messages.
+fn source_events() -> Subscription<Message> {
+    struct SourceEvents;
+
+    subscription::channel(
+        std::any::TypeId::of::<SourceEvents>(),
+        16,
+        |mut output| async move {
+            let (sender, mut receiver) = mpsc::channel(16);
+            let _ = output.send(Message::SourceEventsReady(sender)).await;
+
+            loop {
+                let (generation, event) = receiver.select_next_some().await;
+                let _ = output.send(Message::SourceEvent(generation, event)).await;
+            }
+        },
+    )
 }
 
 fn main() -> iced::Result {
-    App::run(Settings::default())
+    let cli = Cli::parse();
+
+    if let Some(subcommand) = cli.subcommand {
+        if let Err(err) = cli.run(subcommand) {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    let device = cli.device().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
+
+    let config_path = cli.config.clone().or_else(Config::default_path);
+    let file_config = config_path.as_deref().map(Config::load).unwrap_or_default();
+
+    let mut config = file_config.clone();
+    cli.apply(&mut config);
+
+    let mut settings = Settings::with_flags(Flags {
+        config: file_config,
+        launch: config.clone(),
+        config_path,
+        device,
+        fullscreen: cli.fullscreen,
+    });
+
+    settings.window.size = (config.window.width, config.window.height);
+    settings.window.decorations = !cli.borderless;
+    // Closing goes through the app, so a config change that is still waiting gets saved.
+    settings.exit_on_close_request = false;
+
+    if let Some((x, y)) = config.window.position {
+        settings.window.position = window::Position::Specific(x, y);
+    }
+
+    App::run(settings)
 }
@@ -0,0 +1,325 @@
+use std::f32::consts::{PI, SQRT_2, TAU};
+use std::fmt;
+
+use iced::widget::canvas::{path, stroke::Stroke, Frame};
+use iced::{Color, Rectangle, Vector};
+use iced_native::widget::Tree;
+use iced_native::{image, layout, renderer, Element, Layout, Length, Point, Size, Widget};
+
+use crate::gain::GainMapping;
+use crate::visual::Visual;
+use crate::waveform;
+
+const RADIAL_COLOR: Color = Color::BLACK;
+const RADIAL_PEAK_COLOR: Color = Color::from_rgb(0.8, 0., 0.8);
+const PEAK_WIDTH: f32 = 3.;
+/// How far the signal reaches out from the circle, relative to its radius.
+const RADIAL_DEPTH: f32 = 0.5;
+/// Most points drawn around the ring; longer waveform windows are thinned out.
+const WAVEFORM_WINDOW: usize = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RadialSource {
+    #[default]
+    Waveform,
+    Spectrum,
+}
+
+impl RadialSource {
+    pub const ALL: [RadialSource; 2] = [RadialSource::Waveform, RadialSource::Spectrum];
+}
+
+impl fmt::Display for RadialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RadialSource::Waveform => "Waveform Ring",
+            RadialSource::Spectrum => "Spectrum Ring",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RadialConfig {
+    pub source: RadialSource,
+    /// Radius of the inner circle as a fraction of half the shorter side of the canvas.
+    pub radius: f32,
+    /// Degrees per second, negative turns counter-clockwise.
+    pub rotation_speed: f32,
+    /// Draws the data over half the circle and reflects it onto the other half.
+    pub mirror: bool,
+}
+
+impl Default for RadialConfig {
+    fn default() -> Self {
+        RadialConfig {
+            source: RadialSource::default(),
+            radius: 0.5,
+            rotation_speed: 10.,
+            mirror: true,
+        }
+    }
+}
+
+impl RadialConfig {
+    /// Radius in pixels of the inner circle for a canvas of the given bounds.
+    pub fn radius_in(&self, bounds: Rectangle) -> f32 {
+        self.radius * bounds.width.min(bounds.height) / 2.
+    }
+}
+
+pub enum RadialData {
+    /// The oscilloscope's window, the same one the waveform visual shows.
+    Waveform {
+        data: Vec<f32>,
+        channels: u16,
+        gain: GainMapping,
+    },
+    Spectrum {
+        bands: Vec<f32>,
+        /// Held peak of every band, empty when peak hold is off.
+        peaks: Vec<f32>,
+    },
+}
+
+pub struct Radial {
+    pub data: RadialData,
+    pub config: RadialConfig,
+    /// Current rotation in radians, advanced by the app on every tick.
+    pub rotation: f32,
+}
+
+impl Visual for Radial {
+    fn draw(&self, frame: &mut Frame) {
+        let bounds = Rectangle::with_size(frame.size());
+
+        let center = frame.center();
+        let radius = self.config.radius_in(bounds);
+        let depth = radius * RADIAL_DEPTH;
+
+        let values = match self.data {
+            RadialData::Waveform {
+                ref data,
+                channels,
+                gain,
+            } => waveform_points(data, channels, gain),
+            RadialData::Spectrum { ref bands, .. } => bands.clone(),
+        };
+
+        if values.is_empty() {
+            return;
+        }
+
+        // Angle 0 points straight up so an unrotated ring is symmetric around the vertical axis.
+        let span = if self.config.mirror { PI } else { TAU };
+        let step = span / values.len() as f32;
+        let point = |angle: f32, distance: f32| {
+            let angle = angle + self.rotation;
+
+            center + Vector::new(angle.sin() * distance, -angle.cos() * distance)
+        };
+
+        let mut path_builder = path::Builder::new();
+        let sides: &[f32] = if self.config.mirror {
+            &[1., -1.]
+        } else {
+            &[1.]
+        };
+
+        match self.data {
+            RadialData::Waveform { .. } => {
+                for side in sides {
+                    for (i, v) in values.iter().enumerate() {
+                        let position = point(side * i as f32 * step, radius + v * depth);
+
+                        if i == 0 {
+                            path_builder.move_to(position);
+                        } else {
+                            path_builder.line_to(position);
+                        }
+                    }
+
+                    if !self.config.mirror {
+                        path_builder.close();
+                    }
+                }
+            }
+            RadialData::Spectrum { ref peaks, .. } => {
+                for side in sides {
+                    for (i, level) in values.iter().enumerate() {
+                        let angle = side * (i as f32 + 0.5) * step;
+
+                        path_builder.move_to(point(angle, radius));
+                        path_builder.line_to(point(angle, radius + level * depth));
+                    }
+                }
+
+                // Peaks are short arcs across the end of each bar.
+                let mut peak_builder = path::Builder::new();
+
+                for side in sides {
+                    for (i, peak) in peaks.iter().enumerate() {
+                        let distance = radius + peak * depth;
+
+                        peak_builder.move_to(point(side * (i as f32 + 0.15) * step, distance));
+                        peak_builder.line_to(point(side * (i as f32 + 0.85) * step, distance));
+                    }
+                }
+
+                frame.stroke(
+                    &peak_builder.build(),
+                    Stroke::default()
+                        .with_color(RADIAL_PEAK_COLOR)
+                        .with_width(PEAK_WIDTH),
+                );
+            }
+        }
+
+        let width = match self.data {
+            RadialData::Waveform { .. } => 2.,
+            // Bars get most of the arc they cover, leaving a small gap between neighbours.
+            RadialData::Spectrum { .. } => (radius * step * 0.7).max(1.),
+        };
+
+        frame.stroke(
+            &path_builder.build(),
+            Stroke::default().with_color(RADIAL_COLOR).with_width(width),
+        );
+        frame.stroke(
+            &path::Path::circle(center, radius),
+            Stroke::default().with_color(RADIAL_COLOR).with_width(1.),
+        );
+    }
+}
+
+/// Downmixes the window and maps it with the gain, keeping at most [`WAVEFORM_WINDOW`] points.
+fn waveform_points(data: &[f32], channels: u16, gain: GainMapping) -> Vec<f32> {
+    let mono = waveform::downmix(data, channels.max(1) as usize);
+    let step = mono.len().div_ceil(WAVEFORM_WINDOW).max(1);
+
+    mono.iter().step_by(step).map(|x| gain.map(*x)).collect()
+}
+
+/// Draws an image inside the ring's inner circle, sized with the same [`RadialConfig`] as the
+/// [`Radial`] canvas it sits under.
+pub struct Logo<Handle> {
+    pub handle: Handle,
+    pub config: RadialConfig,
+}
+
+impl<Message, Renderer> Widget<Message, Renderer> for Logo<Renderer::Handle>
+where
+    Renderer: image::Renderer,
+{
+    fn width(&self) -> Length {
+        Length::Fill
+    }
+
+    fn height(&self) -> Length {
+        Length::Fill
+    }
+
+    fn layout(&self, _renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        layout::Node::new(limits.max())
+    }
+
+    fn draw(
+        &self,
+        _state: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Renderer::Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor_position: Point,
+        _viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let dimensions = renderer.dimensions(&self.handle);
+
+        if dimensions.width == 0 || dimensions.height == 0 {
+            return;
+        }
+
+        // Largest square that fits in the circle, with the image scaled to fit inside it.
+        let side = self.config.radius_in(bounds) * SQRT_2;
+        let scale = side / dimensions.width.max(dimensions.height) as f32;
+        let size = Size::new(
+            dimensions.width as f32 * scale,
+            dimensions.height as f32 * scale,
+        );
+
+        renderer.draw(
+            self.handle.clone(),
+            Rectangle::new(
+                bounds.center() - Vector::new(size.width / 2., size.height / 2.),
+                size,
+            ),
+        );
+    }
+}
+
+impl<'a, Message, Renderer> From<Logo<Renderer::Handle>> for Element<'a, Message, Renderer>
+where
+    Renderer: 'a + image::Renderer,
+{
+    fn from(logo: Logo<Renderer::Handle>) -> Self {
+        Element::new(logo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gain::{AutoGain, GainConfig};
+
+    fn gain(gain_db: f32) -> GainMapping {
+        AutoGain::new(GainConfig {
+            gain_db,
+            ..GainConfig::default()
+        })
+        .mapping()
+    }
+
+    #[test]
+    fn radius_follows_the_shorter_side() {
+        let config = RadialConfig {
+            radius: 0.5,
+            ..RadialConfig::default()
+        };
+
+        assert_eq!(
+            config.radius_in(Rectangle::with_size(Size::new(800., 400.))),
+            100.
+        );
+        assert_eq!(
+            config.radius_in(Rectangle::with_size(Size::new(300., 900.))),
+            75.
+        );
+    }
+
+    #[test]
+    fn waveform_ring_is_downmixed_and_gained() {
+        let data = [0.2, 0.4, -0.1, -0.3, 0., 0.];
+
+        let points = waveform_points(&data, 2, gain(20. * 2f32.log10()));
+
+        assert_eq!(points.len(), 3);
+        for (point, expected) in points.iter().zip([0.6, -0.4, 0.]) {
+            assert!((point - expected).abs() < 1e-5, "{point} != {expected}");
+        }
+    }
+
+    #[test]
+    fn long_windows_are_thinned_out() {
+        let data = (0..WAVEFORM_WINDOW * 3 + 1)
+            .map(|x| x as f32 / 1e5)
+            .collect::<Vec<_>>();
+
+        let points = waveform_points(&data, 1, gain(0.));
+
+        assert!(points.len() <= WAVEFORM_WINDOW);
+        assert!(points.len() > WAVEFORM_WINDOW / 2);
+        // Evenly spaced through the window, starting at its first frame.
+        assert_eq!(points[0], 0.);
+        assert!((points[1] - 4. / 1e5).abs() < 1e-7);
+    }
+}
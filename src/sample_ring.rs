@@ -0,0 +1,217 @@
+use std::hint;
+use std::sync::atomic::{self, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Fixed-capacity single-producer/single-consumer ring of `f32` samples.
+///
+/// The producer (the audio callback) overwrites the oldest samples once the ring is full and
+/// never allocates or blocks. The consumer (the canvas) copies out the most recent samples
+/// whenever it draws, without waiting for new data to arrive.
+pub struct SampleRing {
+    samples: Box<[AtomicU32]>,
+    mask: usize,
+    written: AtomicUsize,
+    /// Where the push in progress will end, announced before it overwrites anything.
+    claimed: AtomicUsize,
+    created: Instant,
+    /// Twice the number of pushes, plus one while `last_push` is being updated.
+    push_sequence: AtomicUsize,
+    /// Nanoseconds from `created` to the last push.
+    last_push: AtomicU64,
+}
+
+impl SampleRing {
+    /// Creates a ring holding at least `capacity` samples, rounded up to a power of two.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1).next_power_of_two();
+
+        SampleRing {
+            samples: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+            mask: capacity - 1,
+            written: AtomicUsize::new(0),
+            claimed: AtomicUsize::new(0),
+            created: Instant::now(),
+            push_sequence: AtomicUsize::new(0),
+            last_push: AtomicU64::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Total number of samples pushed since the ring was created.
+    pub fn written(&self) -> usize {
+        self.written.load(Ordering::Acquire)
+    }
+
+    /// Appends samples to the ring. Must only be called from the single producer.
+    pub fn push_slice(&self, data: &[f32]) {
+        self.push_iter(data.iter().copied());
+    }
+
+    /// Appends samples to the ring. Must only be called from the single producer.
+    pub fn push_iter(&self, data: impl ExactSizeIterator<Item = f32>) {
+        let start = self.written.load(Ordering::Relaxed);
+        let mut end = start;
+
+        // Lets a snapshot running alongside find out which of its samples were overwritten.
+        self.claimed
+            .store(start.wrapping_add(data.len()), Ordering::Relaxed);
+        atomic::fence(Ordering::Release);
+
+        for sample in data {
+            self.samples[end & self.mask].store(sample.to_bits(), Ordering::Relaxed);
+            end = end.wrapping_add(1);
+        }
+
+        self.written.store(end, Ordering::Release);
+
+        let sequence = self.push_sequence.load(Ordering::Relaxed);
+        self.push_sequence.store(sequence + 1, Ordering::Relaxed);
+        atomic::fence(Ordering::Release);
+        self.last_push
+            .store(self.created.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        self.push_sequence.store(sequence + 2, Ordering::Release);
+    }
+
+    /// Number of pushes so far, i.e. audio callbacks for a device, and how long after the ring
+    /// was created the last one happened.
+    pub fn pushes(&self) -> (usize, Duration) {
+        loop {
+            let sequence = self.push_sequence.load(Ordering::Acquire);
+            let last_push = self.last_push.load(Ordering::Relaxed);
+            atomic::fence(Ordering::Acquire);
+
+            // Retry if a push was updating the time, so it isn't paired with the wrong count.
+            if sequence % 2 == 0 && self.push_sequence.load(Ordering::Relaxed) == sequence {
+                return (sequence / 2, Duration::from_nanos(last_push));
+            }
+
+            hint::spin_loop();
+        }
+    }
+
+    /// Replaces the contents of `out` with up to `len` of the most recent samples, oldest first.
+    ///
+    /// Returns the number of samples written when the snapshot was taken, i.e. the position just
+    /// past its last sample. Samples the producer overwrote while they were being copied are left
+    /// out, so `out` may come back shorter than asked for.
+    pub fn snapshot(&self, out: &mut Vec<f32>, len: usize) -> usize {
+        let end = self.written();
+        let len = len.min(end).min(self.capacity());
+
+        out.clear();
+        out.extend(
+            (end - len..end)
+                .map(|i| f32::from_bits(self.samples[i & self.mask].load(Ordering::Relaxed))),
+        );
+
+        atomic::fence(Ordering::Acquire);
+        let overwritten = self
+            .claimed
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.capacity())
+            .saturating_sub(end - len);
+        out.drain(..overwritten.min(len));
+
+        end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn rounds_capacity_up_to_a_power_of_two() {
+        assert_eq!(SampleRing::new(5).capacity(), 8);
+        assert_eq!(SampleRing::new(0).capacity(), 1);
+    }
+
+    #[test]
+    fn snapshots_the_latest_samples() {
+        let ring = SampleRing::new(8);
+        let mut out = Vec::new();
+
+        ring.push_slice(&[1., 2., 3.]);
+        ring.snapshot(&mut out, 2);
+        assert_eq!(out, [2., 3.]);
+
+        // Asking for more than was written gives everything there is.
+        ring.snapshot(&mut out, 10);
+        assert_eq!(out, [1., 2., 3.]);
+        assert_eq!(ring.written(), 3);
+    }
+
+    #[test]
+    fn wraps_around_keeping_the_newest() {
+        let ring = SampleRing::new(4);
+        let mut out = Vec::new();
+
+        ring.push_iter((0..10).map(|x| x as f32));
+        ring.snapshot(&mut out, 10);
+
+        assert_eq!(ring.written(), 10);
+        assert_eq!(out, [6., 7., 8., 9.]);
+    }
+
+    #[test]
+    fn drops_samples_overwritten_during_a_snapshot() {
+        // Sample values count up, wrapping before they stop being exact in an f32.
+        const WRAP: usize = 1 << 24;
+
+        let ring = Arc::new(SampleRing::new(64));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let producer = {
+            let ring = Arc::clone(&ring);
+            let running = Arc::clone(&running);
+
+            thread::spawn(move || {
+                let mut next = 0;
+
+                while running.load(Ordering::Relaxed) {
+                    ring.push_iter((next..next + 16).map(|x| (x % WRAP) as f32));
+                    next += 16;
+                }
+            })
+        };
+
+        let mut out = Vec::new();
+
+        for _ in 0..10_000 {
+            let end = ring.snapshot(&mut out, 64);
+
+            if let Some(last) = out.last() {
+                assert_eq!(*last as usize, (end - 1) % WRAP);
+            }
+
+            for pair in out.windows(2) {
+                assert_eq!((pair[0] as usize + 1) % WRAP, pair[1] as usize);
+            }
+        }
+
+        running.store(false, Ordering::Relaxed);
+        producer.join().unwrap();
+    }
+
+    #[test]
+    fn counts_pushes() {
+        let ring = SampleRing::new(8);
+
+        assert_eq!(ring.pushes().0, 0);
+
+        ring.push_slice(&[1., 2.]);
+        ring.push_slice(&[3.]);
+        let (pushes, first) = ring.pushes();
+        ring.push_slice(&[]);
+
+        assert_eq!(pushes, 2);
+        assert!(ring.pushes().1 >= first);
+    }
+}
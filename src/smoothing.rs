@@ -0,0 +1,180 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmoothingConfig {
+    /// Time constant in milliseconds for easing the waveform towards each new frame, 0 is off.
+    pub waveform_ms: f32,
+    pub peak_hold: bool,
+    /// How long a peak stays put before it starts to fall.
+    pub hold_ms: f32,
+    /// How fast a released peak falls, in full canvas heights per second.
+    pub decay: f32,
+}
+
+impl Default for SmoothingConfig {
+    fn default() -> Self {
+        SmoothingConfig {
+            waveform_ms: 0.,
+            peak_hold: false,
+            hold_ms: 500.,
+            decay: 0.5,
+        }
+    }
+}
+
+/// Values closer than this to their target are snapped onto it, so easing comes to rest.
+const SETTLED: f32 = 1e-4;
+
+/// Eases displayed values towards their latest targets, stepped once per tick.
+#[derive(Debug, Default)]
+pub struct Smoothed {
+    values: Vec<f32>,
+}
+
+impl Smoothed {
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// Returns whether any value visibly moved.
+    pub fn update(&mut self, target: &[f32], time_ms: f32, elapsed: f32) -> bool {
+        // Nothing sensible to ease from when the shape changes, start over.
+        if time_ms <= 0. || self.values.len() != target.len() {
+            let moved = self.values != target;
+
+            self.values.clear();
+            self.values.extend_from_slice(target);
+            return moved;
+        }
+
+        let amount = 1. - (-elapsed * 1000. / time_ms).exp();
+        let mut moved = false;
+
+        for (value, target) in self.values.iter_mut().zip(target) {
+            if (target - *value).abs() > SETTLED {
+                *value += (target - *value) * amount;
+                moved = true;
+            } else {
+                *value = *target;
+            }
+        }
+
+        moved
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+/// Remembers the highest recent level of every bar and lets it fall after a hold time.
+#[derive(Debug, Default)]
+pub struct PeakHold {
+    peaks: Vec<f32>,
+    /// Seconds left before each peak starts to fall.
+    holding: Vec<f32>,
+}
+
+impl PeakHold {
+    pub fn peaks(&self) -> &[f32] {
+        &self.peaks
+    }
+
+    /// Returns whether any peak moved.
+    pub fn update(&mut self, levels: &[f32], config: &SmoothingConfig, elapsed: f32) -> bool {
+        if self.peaks.len() != levels.len() {
+            self.peaks = levels.to_vec();
+            self.holding = vec![config.hold_ms / 1000.; levels.len()];
+            return true;
+        }
+
+        let mut moved = false;
+
+        for ((peak, holding), level) in self.peaks.iter_mut().zip(&mut self.holding).zip(levels) {
+            let previous = *peak;
+
+            if *level >= *peak {
+                *peak = *level;
+                *holding = config.hold_ms / 1000.;
+            } else if *holding > 0. {
+                *holding -= elapsed;
+            } else {
+                *peak = (*peak - config.decay * elapsed).max(*level);
+            }
+
+            moved |= *peak != previous;
+        }
+
+        moved
+    }
+
+    pub fn clear(&mut self) {
+        self.peaks.clear();
+        self.holding.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eases_towards_the_target_and_settles() {
+        let mut smoothed = Smoothed::default();
+        smoothed.update(&[0.], 100., 0.01);
+
+        // One time constant gets about 63% of the way there.
+        for _ in 0..10 {
+            assert!(smoothed.update(&[1.], 100., 0.01));
+        }
+        assert!((smoothed.values()[0] - (1. - (-1f32).exp())).abs() < 1e-3);
+
+        let mut ticks = 0;
+        while smoothed.update(&[1.], 100., 0.01) {
+            ticks += 1;
+            assert!(ticks < 1000, "never settled");
+        }
+        assert_eq!(smoothed.values(), [1.]);
+    }
+
+    #[test]
+    fn jumps_when_off_or_reshaped() {
+        let mut smoothed = Smoothed::default();
+        smoothed.update(&[0., 0.], 100., 0.01);
+
+        assert!(smoothed.update(&[1., -1.], 0., 0.01));
+        assert_eq!(smoothed.values(), [1., -1.]);
+
+        assert!(smoothed.update(&[0.5], 100., 0.01));
+        assert_eq!(smoothed.values(), [0.5]);
+    }
+
+    #[test]
+    fn peaks_hold_then_fall_at_the_decay_rate() {
+        let config = SmoothingConfig {
+            peak_hold: true,
+            hold_ms: 500.,
+            decay: 0.5,
+            ..SmoothingConfig::default()
+        };
+        let mut peaks = PeakHold::default();
+        peaks.update(&[1.], &config, 0.125);
+
+        // Held for the full 500 ms.
+        for _ in 0..4 {
+            assert!(!peaks.update(&[0.], &config, 0.125));
+            assert_eq!(peaks.peaks(), [1.]);
+        }
+
+        // Then falls 0.5 per second, down to the level but not past it.
+        assert!(peaks.update(&[0.], &config, 0.125));
+        assert_eq!(peaks.peaks(), [0.9375]);
+        assert!(peaks.update(&[0.], &config, 1.));
+        assert_eq!(peaks.peaks(), [0.4375]);
+        assert!(peaks.update(&[0.25], &config, 1.));
+        assert_eq!(peaks.peaks(), [0.25]);
+
+        // A new high is caught straight away and held again.
+        assert!(peaks.update(&[0.75], &config, 0.125));
+        assert!(!peaks.update(&[0.], &config, 0.125));
+        assert_eq!(peaks.peaks(), [0.75]);
+    }
+}
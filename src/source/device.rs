@@ -0,0 +1,676 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use cpal::platform::Device;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    BufferSize, BuildStreamError, DevicesError, FromSample, HostId, Sample, SampleFormat,
+    SampleRate, SizedSample, Stream, StreamConfig, StreamError, SupportedBufferSize,
+    SupportedStreamConfig, SupportedStreamConfigRange,
+};
+
+use super::{AudioSource, CaptureError, EventSender, Result, SourceEvent, SAMPLE_RING_CAPACITY};
+use crate::sample_ring::SampleRing;
+
+/// Backend errors within [`STREAM_ERROR_WINDOW`] of each other before the stream counts as lost.
+const STREAM_ERROR_LIMIT: usize = 10;
+const STREAM_ERROR_WINDOW: Duration = Duration::from_secs(1);
+const COMMON_SAMPLE_RATES: [u32; 11] = [
+    8000, 11025, 16000, 22050, 32000, 44100, 48000, 88200, 96000, 176400, 192000,
+];
+const COMMON_BUFFER_SIZES: [u32; 9] = [32, 64, 128, 256, 512, 1024, 2048, 4096, 8192];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeviceKind {
+    Input,
+    /// An output device, captured through loopback or its monitor where the host supports it.
+    Output,
+    /// A PulseAudio/PipeWire sink monitor, named after its monitor source.
+    Monitor,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceId {
+    pub host: HostId,
+    pub kind: DeviceKind,
+    pub name: String,
+}
+
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.host.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BufferChoice {
+    #[default]
+    Default,
+    Frames(u32),
+}
+
+impl fmt::Display for BufferChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BufferChoice::Default => f.write_str("Default buffer"),
+            BufferChoice::Frames(frames) => write!(f, "{frames} frames"),
+        }
+    }
+}
+
+/// A specific stream config picked from one of the device's supported ranges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureConfig {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub sample_format: SampleFormat,
+    pub buffer_size: BufferChoice,
+}
+
+impl CaptureConfig {
+    fn stream_config(&self) -> StreamConfig {
+        StreamConfig {
+            channels: self.channels,
+            sample_rate: SampleRate(self.sample_rate),
+            buffer_size: match self.buffer_size {
+                BufferChoice::Frames(frames) => BufferSize::Fixed(frames),
+                BufferChoice::Default => BufferSize::Default,
+            },
+        }
+    }
+}
+
+impl fmt::Display for CaptureConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ch, {} Hz, {}",
+            self.channels, self.sample_rate, self.sample_format
+        )
+    }
+}
+
+/// A device along with the config to open it with; `None` uses the device's default config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceSelection {
+    pub id: DeviceId,
+    pub config: Option<CaptureConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    pub id: DeviceId,
+    pub label: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct HostDevices {
+    pub host: HostId,
+    pub inputs: Vec<DeviceInfo>,
+    pub outputs: Vec<DeviceInfo>,
+    /// Why the host's devices couldn't be listed, if they couldn't. The other hosts are still
+    /// listed.
+    pub error: Option<String>,
+}
+
+/// Lists the input and output devices of every host available on this platform. Hosts that
+/// can't be opened (e.g. JACK without a running server) are left out.
+pub fn list_devices() -> Vec<HostDevices> {
+    let mut hosts = Vec::new();
+
+    for host_id in cpal::available_hosts() {
+        let Ok(host) = cpal::host_from_id(host_id) else {
+            continue;
+        };
+
+        let default_input = host.default_input_device().and_then(|x| x.name().ok());
+        let default_output = host.default_output_device().and_then(|x| x.name().ok());
+
+        let mut errors = Vec::new();
+        let mut describe = |devices: std::result::Result<_, DevicesError>, kind, default| {
+            devices
+                .map(|devices| describe_devices(devices, host_id, kind, default))
+                .unwrap_or_else(|err| {
+                    errors.push(err.to_string());
+                    Vec::new()
+                })
+        };
+
+        let inputs = describe(host.input_devices(), DeviceKind::Input, default_input);
+        let outputs = describe(host.output_devices(), DeviceKind::Output, default_output);
+
+        hosts.push(HostDevices {
+            host: host_id,
+            inputs,
+            outputs,
+            error: (!errors.is_empty()).then(|| errors.join(", ")),
+        });
+    }
+
+    hosts
+}
+
+fn describe_devices(
+    devices: impl Iterator<Item = Device>,
+    host: HostId,
+    kind: DeviceKind,
+    default: Option<String>,
+) -> Vec<DeviceInfo> {
+    devices
+        .filter_map(|device| device.name().ok())
+        .map(|name| DeviceInfo {
+            label: name.clone(),
+            is_default: default.as_ref() == Some(&name),
+            id: DeviceId { host, kind, name },
+        })
+        .collect()
+}
+
+fn find_device(id: &DeviceId) -> Result<Device> {
+    let host = cpal::host_from_id(id.host)?;
+    let mut devices = match id.kind {
+        DeviceKind::Input => host.input_devices()?,
+        DeviceKind::Output => host.output_devices()?,
+        // Recorded through the sound server rather than opened as a cpal device.
+        DeviceKind::Monitor => return Err(CaptureError::DeviceNotFound(id.name.clone())),
+    };
+
+    devices
+        .find(|x| x.name().map(|y| y == id.name).unwrap_or(false))
+        .ok_or_else(|| CaptureError::DeviceNotFound(id.name.clone()))
+}
+
+/// Whether the device can currently be found on its host.
+pub fn device_exists(id: &DeviceId) -> bool {
+    match id.kind {
+        #[cfg(target_os = "linux")]
+        DeviceKind::Monitor => super::monitor::monitor_exists(id),
+        _ => find_device(id).is_ok(),
+    }
+}
+
+/// The config the device is opened with when none is picked.
+pub fn default_capture_config(id: &DeviceId) -> Result<CaptureConfig> {
+    #[cfg(target_os = "linux")]
+    if id.kind == DeviceKind::Monitor {
+        return super::monitor::capture_config(id);
+    }
+
+    let config = default_config(&find_device(id)?, id.kind)
+        .ok_or_else(|| CaptureError::NoUsableConfig(id.name.clone()))?;
+
+    Ok(CaptureConfig {
+        channels: config.channels(),
+        sample_rate: config.sample_rate().0,
+        sample_format: config.sample_format(),
+        buffer_size: BufferChoice::Default,
+    })
+}
+
+/// Every stream config range the device can be captured with.
+pub fn supported_configs(id: &DeviceId) -> Result<Vec<SupportedStreamConfigRange>> {
+    #[cfg(target_os = "linux")]
+    if id.kind == DeviceKind::Monitor {
+        return super::monitor::supported_configs(id);
+    }
+
+    Ok(supported_ranges(&find_device(id)?, id.kind))
+}
+
+fn supported_ranges(device: &Device, kind: DeviceKind) -> Vec<SupportedStreamConfigRange> {
+    let configs = device
+        .supported_input_configs()
+        .map(|x| x.collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    // Loopback capture of an output device (e.g. WASAPI) uses the output configs.
+    if configs.is_empty() && kind == DeviceKind::Output {
+        return device
+            .supported_output_configs()
+            .map(|x| x.collect())
+            .unwrap_or_default();
+    }
+
+    configs
+}
+
+/// The common sample rates inside the range, plus its own bounds.
+pub fn sample_rates(range: &SupportedStreamConfigRange) -> Vec<u32> {
+    let (min, max) = (range.min_sample_rate().0, range.max_sample_rate().0);
+    let mut sample_rates = COMMON_SAMPLE_RATES
+        .into_iter()
+        .filter(|x| (min..=max).contains(x))
+        .chain([min, max])
+        .collect::<Vec<u32>>();
+
+    sample_rates.sort_unstable();
+    sample_rates.dedup();
+
+    sample_rates
+}
+
+/// The host default plus the power-of-two buffer sizes allowed by at least one of the ranges.
+pub fn buffer_sizes(ranges: &[SupportedStreamConfigRange]) -> Vec<BufferChoice> {
+    let sizes = COMMON_BUFFER_SIZES.into_iter().filter(|size| {
+        ranges.iter().any(|x| match *x.buffer_size() {
+            SupportedBufferSize::Range { min, max } => (min..=max).contains(size),
+            SupportedBufferSize::Unknown => true,
+        })
+    });
+
+    [BufferChoice::Default]
+        .into_iter()
+        .chain(sizes.map(BufferChoice::Frames))
+        .collect()
+}
+
+/// Summarises a supported range, e.g. `2 ch, f32, 8000-192000 Hz, 64-8192 frames`.
+pub fn describe_range(range: &SupportedStreamConfigRange) -> String {
+    let buffer = match *range.buffer_size() {
+        SupportedBufferSize::Range { min, max } => format!("{min}-{max} frames"),
+        SupportedBufferSize::Unknown => "any buffer".to_string(),
+    };
+
+    format!(
+        "{} ch, {}, {}-{} Hz, {}",
+        range.channels(),
+        range.sample_format(),
+        range.min_sample_rate().0,
+        range.max_sample_rate().0,
+        buffer
+    )
+}
+
+/// Captures from a cpal device by opening an input stream on it.
+pub struct DeviceSource {
+    id: DeviceId,
+    input: Input,
+    config: StreamConfig,
+    sample_format: SampleFormat,
+    samples: Arc<SampleRing>,
+    events: Option<EventSender>,
+    /// Stream errors so far, including the ones the stream recovered from.
+    errors: Arc<AtomicUsize>,
+}
+
+/// What a [`DeviceSource`] captures from.
+enum Input {
+    Device {
+        device: Device,
+        stream: Option<Stream>,
+    },
+    /// A sink monitor, recorded only while the source is started.
+    #[cfg(target_os = "linux")]
+    Monitor(Option<super::monitor::Recording>),
+}
+
+impl DeviceSource {
+    /// Opens the selected device. Stream errors are reported on `events`, if given.
+    pub fn new(selection: &DeviceSelection, events: Option<EventSender>) -> Result<Self> {
+        let (input, config, sample_format) = match selection.id.kind {
+            #[cfg(target_os = "linux")]
+            DeviceKind::Monitor => {
+                let config = match selection.config {
+                    Some(config) => config,
+                    None => super::monitor::capture_config(&selection.id)?,
+                };
+
+                (
+                    Input::Monitor(None),
+                    config.stream_config(),
+                    config.sample_format,
+                )
+            }
+            _ => {
+                let device = find_device(&selection.id)?;
+
+                let (config, sample_format) = match selection.config {
+                    Some(config) => (config.stream_config(), config.sample_format),
+                    None => {
+                        let config =
+                            default_config(&device, selection.id.kind).ok_or_else(|| {
+                                CaptureError::NoUsableConfig(selection.id.name.clone())
+                            })?;
+
+                        (config.config(), config.sample_format())
+                    }
+                };
+
+                (
+                    Input::Device {
+                        device,
+                        stream: None,
+                    },
+                    config,
+                    sample_format,
+                )
+            }
+        };
+
+        Ok(DeviceSource {
+            id: selection.id.clone(),
+            input,
+            config,
+            sample_format,
+            samples: Arc::new(SampleRing::new(SAMPLE_RING_CAPACITY)),
+            events,
+            errors: Arc::default(),
+        })
+    }
+
+    /// Number of errors the stream reported, including ones it recovered from.
+    pub fn stream_errors(&self) -> usize {
+        self.errors.load(Ordering::Relaxed)
+    }
+}
+
+fn is_usable(sample_format: SampleFormat) -> bool {
+    matches!(
+        sample_format,
+        SampleFormat::I8
+            | SampleFormat::I16
+            | SampleFormat::I32
+            | SampleFormat::I64
+            | SampleFormat::U8
+            | SampleFormat::U16
+            | SampleFormat::U32
+            | SampleFormat::U64
+            | SampleFormat::F32
+            | SampleFormat::F64
+    )
+}
+
+/// The default config if its sample format can be converted, otherwise the best of the
+/// supported configs that can.
+fn default_config(device: &Device, kind: DeviceKind) -> Option<SupportedStreamConfig> {
+    let default = match kind {
+        DeviceKind::Input | DeviceKind::Monitor => device.default_input_config(),
+        DeviceKind::Output => device
+            .default_input_config()
+            .or_else(|_| device.default_output_config()),
+    };
+
+    if let Ok(config) = default {
+        if is_usable(config.sample_format()) {
+            return Some(config);
+        }
+    }
+
+    supported_ranges(device, kind)
+        .into_iter()
+        .filter(|x| is_usable(x.sample_format()))
+        .max_by(|a, b| a.cmp_default_heuristics(b))
+        .map(|x| x.with_max_sample_rate())
+}
+
+/// Tells a gone device apart from the odd backend error, like an ALSA xrun, that the stream
+/// recovers from by itself.
+#[derive(Debug, Default)]
+struct StreamErrors {
+    count: usize,
+    since: Option<Instant>,
+    lost: bool,
+    total: Arc<AtomicUsize>,
+}
+
+impl StreamErrors {
+    fn record(&mut self, err: &StreamError, now: Instant) {
+        self.total.fetch_add(1, Ordering::Relaxed);
+
+        match err {
+            StreamError::DeviceNotAvailable => self.lost = true,
+            StreamError::BackendSpecific { .. } => {
+                match self.since {
+                    Some(since) if now.duration_since(since) < STREAM_ERROR_WINDOW => {
+                        self.count += 1;
+                    }
+                    _ => {
+                        self.since = Some(now);
+                        self.count = 1;
+                    }
+                }
+
+                self.lost |= self.count >= STREAM_ERROR_LIMIT;
+            }
+        }
+    }
+
+    /// Whether any error so far means the stream is gone. Once lost, it stays lost.
+    fn is_lost(&self) -> bool {
+        self.lost
+    }
+}
+
+fn err_fn(err: StreamError, errors: &mut StreamErrors, events: &mut Option<EventSender>) {
+    eprintln!("an error occurred on stream: {}", err);
+
+    let was_lost = errors.is_lost();
+    errors.record(&err, Instant::now());
+
+    // Only the first failure is reported; a dead stream may keep erroring until it is dropped.
+    if errors.is_lost() && !was_lost {
+        if let Some(events) = events {
+            events.send(SourceEvent::StreamLost(err.to_string()));
+        }
+    }
+}
+
+fn input_data_fn<T>(data: &[T], _: &cpal::InputCallbackInfo, samples: &SampleRing)
+where
+    T: Sample,
+    f32: FromSample<T>,
+{
+    samples.push_iter(data.iter().map(|sample| f32::from_sample(*sample)));
+}
+
+fn build_stream<T>(
+    device: &Device,
+    config: &StreamConfig,
+    samples: Arc<SampleRing>,
+    mut errors: StreamErrors,
+    mut events: Option<EventSender>,
+) -> std::result::Result<Stream, BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    device.build_input_stream(
+        config,
+        move |data: &[T], cb_info: &cpal::InputCallbackInfo| {
+            input_data_fn(data, cb_info, &samples);
+        },
+        move |err| err_fn(err, &mut errors, &mut events),
+        None,
+    )
+}
+
+impl AudioSource for DeviceSource {
+    fn start(&mut self) -> Result<()> {
+        let (device, stream) = match self.input {
+            Input::Device {
+                ref device,
+                ref mut stream,
+            } => (device, stream),
+            #[cfg(target_os = "linux")]
+            Input::Monitor(ref mut recording) => {
+                if recording.is_none() {
+                    *recording = Some(super::monitor::record(
+                        &self.id,
+                        &self.config,
+                        Arc::clone(&self.samples),
+                        Arc::clone(&self.errors),
+                        self.events.clone(),
+                    )?);
+                }
+
+                return Ok(());
+            }
+        };
+
+        if stream.is_none() {
+            let config = &self.config;
+            let samples = Arc::clone(&self.samples);
+            let events = self.events.clone();
+            let errors = StreamErrors {
+                total: Arc::clone(&self.errors),
+                ..StreamErrors::default()
+            };
+
+            *stream = Some(match self.sample_format {
+                SampleFormat::I8 => build_stream::<i8>(device, config, samples, errors, events)?,
+                SampleFormat::I16 => build_stream::<i16>(device, config, samples, errors, events)?,
+                SampleFormat::I32 => build_stream::<i32>(device, config, samples, errors, events)?,
+                SampleFormat::I64 => build_stream::<i64>(device, config, samples, errors, events)?,
+                SampleFormat::U8 => build_stream::<u8>(device, config, samples, errors, events)?,
+                SampleFormat::U16 => build_stream::<u16>(device, config, samples, errors, events)?,
+                SampleFormat::U32 => build_stream::<u32>(device, config, samples, errors, events)?,
+                SampleFormat::U64 => build_stream::<u64>(device, config, samples, errors, events)?,
+                SampleFormat::F32 => build_stream::<f32>(device, config, samples, errors, events)?,
+                SampleFormat::F64 => build_stream::<f64>(device, config, samples, errors, events)?,
+                sample_format => return Err(CaptureError::UnsupportedSampleFormat(sample_format)),
+            });
+        }
+
+        if let Some(stream) = stream {
+            stream.play()?;
+        }
+
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        match self.input {
+            Input::Device {
+                stream: Some(ref stream),
+                ..
+            } => stream.pause()?,
+            Input::Device { stream: None, .. } => {}
+            // Stops the recorder; starting again runs a new one.
+            #[cfg(target_os = "linux")]
+            Input::Monitor(ref mut recording) => *recording = None,
+        }
+
+        Ok(())
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.config.sample_rate.0
+    }
+
+    fn channels(&self) -> u16 {
+        self.config.channels
+    }
+
+    fn samples(&self) -> Arc<SampleRing> {
+        Arc::clone(&self.samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cpal::BackendSpecificError;
+    use iced::futures::channel::mpsc;
+
+    use super::*;
+
+    fn backend_error() -> StreamError {
+        StreamError::BackendSpecific {
+            err: BackendSpecificError {
+                description: "xrun".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn converts_sample_formats_to_f32() {
+        let cases = [
+            ("u8 min", f32::from_sample(u8::MIN), -1.),
+            ("u8 mid", f32::from_sample(128u8), 0.),
+            ("u8 max", f32::from_sample(u8::MAX), 127. / 128.),
+            ("u16 mid", f32::from_sample(32768u16), 0.),
+            ("u16 max", f32::from_sample(u16::MAX), 32767. / 32768.),
+            ("u32 mid", f32::from_sample(1u32 << 31), 0.),
+            ("u64 mid", f32::from_sample(1u64 << 63), 0.),
+            ("i8 min", f32::from_sample(i8::MIN), -1.),
+            ("i8 zero", f32::from_sample(0i8), 0.),
+            ("i16 min", f32::from_sample(i16::MIN), -1.),
+            ("i16 zero", f32::from_sample(0i16), 0.),
+            ("i16 max", f32::from_sample(i16::MAX), 32767. / 32768.),
+            ("i32 min", f32::from_sample(i32::MIN), -1.),
+            ("i32 max", f32::from_sample(i32::MAX), 1.),
+            ("i64 min", f32::from_sample(i64::MIN), -1.),
+            ("f64 full scale", f32::from_sample(-1f64), -1.),
+            ("f64 half", f32::from_sample(0.5f64), 0.5),
+        ];
+
+        for (name, converted, expected) in cases {
+            assert!(
+                (converted - expected).abs() < 1e-6,
+                "{name}: {converted} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn unplugged_device_is_lost() {
+        let mut errors = StreamErrors::default();
+        errors.record(&StreamError::DeviceNotAvailable, Instant::now());
+
+        assert!(errors.is_lost());
+        assert_eq!(errors.total.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn occasional_backend_errors_are_survived() {
+        let mut errors = StreamErrors::default();
+        let start = Instant::now();
+
+        for i in 0..STREAM_ERROR_LIMIT * 2 {
+            errors.record(&backend_error(), start + STREAM_ERROR_WINDOW / 2 * i as u32);
+
+            assert!(!errors.is_lost());
+        }
+
+        assert_eq!(errors.total.load(Ordering::Relaxed), STREAM_ERROR_LIMIT * 2);
+    }
+
+    #[test]
+    fn burst_of_backend_errors_is_lost() {
+        let mut errors = StreamErrors::default();
+        let now = Instant::now();
+
+        for _ in 1..STREAM_ERROR_LIMIT {
+            errors.record(&backend_error(), now);
+            assert!(!errors.is_lost());
+        }
+
+        errors.record(&backend_error(), now);
+        assert!(errors.is_lost());
+
+        // Asking again doesn't count as another error.
+        assert!(errors.is_lost());
+        assert_eq!(errors.total.load(Ordering::Relaxed), STREAM_ERROR_LIMIT);
+    }
+
+    #[test]
+    fn reports_a_lost_stream_once() {
+        let (sender, mut receiver) = mpsc::channel(16);
+        let mut events = Some(EventSender::new(3, sender));
+        let mut errors = StreamErrors::default();
+
+        for _ in 0..3 {
+            err_fn(StreamError::DeviceNotAvailable, &mut errors, &mut events);
+        }
+
+        assert!(matches!(
+            receiver.try_next(),
+            Ok(Some((3, SourceEvent::StreamLost(_))))
+        ));
+        assert!(receiver.try_next().is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use cpal::{
+    BuildStreamError, DevicesError, HostUnavailable, PauseStreamError, PlayStreamError,
+    SampleFormat,
+};
+
+/// Everything that can go wrong while finding, opening or running an [`super::AudioSource`].
+#[derive(Debug)]
+pub enum CaptureError {
+    HostUnavailable,
+    Devices(DevicesError),
+    DeviceNotFound(String),
+    NoUsableConfig(String),
+    UnsupportedSampleFormat(SampleFormat),
+    BuildStream(BuildStreamError),
+    PlayStream(PlayStreamError),
+    PauseStream(PauseStreamError),
+    Io(io::Error),
+    Decode(symphonia::core::errors::Error),
+    NoAudio(PathBuf),
+    WorkerPanicked(&'static str),
+    StreamLost(String),
+    Parec(io::Error),
+    #[cfg(feature = "jack")]
+    Jack(jack::Error),
+    #[cfg(feature = "jack")]
+    NoPorts,
+    #[cfg(feature = "jack")]
+    TooManyPorts {
+        requested: usize,
+        max: usize,
+    },
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::HostUnavailable => f.write_str("audio host is not available"),
+            CaptureError::Devices(err) => write!(f, "failed to list audio devices: {err}"),
+            CaptureError::DeviceNotFound(name) => write!(f, "device \"{name}\" is not available"),
+            CaptureError::NoUsableConfig(name) => write!(
+                f,
+                "device \"{name}\" has no stream config with a usable sample format"
+            ),
+            CaptureError::UnsupportedSampleFormat(format) => {
+                write!(f, "sample format {format} is not supported")
+            }
+            CaptureError::BuildStream(err) => write!(f, "failed to open stream: {err}"),
+            CaptureError::PlayStream(err) => write!(f, "failed to start stream: {err}"),
+            CaptureError::PauseStream(err) => write!(f, "failed to pause stream: {err}"),
+            CaptureError::Io(err) => write!(f, "failed to read file: {err}"),
+            CaptureError::Decode(err) => write!(f, "failed to decode file: {err}"),
+            CaptureError::NoAudio(path) => write!(f, "no audio found in {}", path.display()),
+            CaptureError::WorkerPanicked(name) => write!(f, "{name} thread panicked"),
+            CaptureError::StreamLost(reason) => write!(f, "stream lost: {reason}"),
+            CaptureError::Parec(err) => write!(f, "failed to run parec: {err}"),
+            #[cfg(feature = "jack")]
+            CaptureError::Jack(err) => write!(f, "JACK client failed: {err}"),
+            #[cfg(feature = "jack")]
+            CaptureError::NoPorts => f.write_str("no JACK input ports given"),
+            #[cfg(feature = "jack")]
+            CaptureError::TooManyPorts { requested, max } => {
+                write!(
+                    f,
+                    "asked for {requested} JACK input ports, at most {max} are supported"
+                )
+            }
+        }
+    }
+}
+
+impl Error for CaptureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CaptureError::Devices(err) => Some(err),
+            CaptureError::BuildStream(err) => Some(err),
+            CaptureError::PlayStream(err) => Some(err),
+            CaptureError::PauseStream(err) => Some(err),
+            CaptureError::Io(err) => Some(err),
+            CaptureError::Decode(err) => Some(err),
+            CaptureError::Parec(err) => Some(err),
+            #[cfg(feature = "jack")]
+            CaptureError::Jack(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<HostUnavailable> for CaptureError {
+    fn from(_: HostUnavailable) -> Self {
+        CaptureError::HostUnavailable
+    }
+}
+
+impl From<DevicesError> for CaptureError {
+    fn from(err: DevicesError) -> Self {
+        CaptureError::Devices(err)
+    }
+}
+
+impl From<BuildStreamError> for CaptureError {
+    fn from(err: BuildStreamError) -> Self {
+        CaptureError::BuildStream(err)
+    }
+}
+
+impl From<PlayStreamError> for CaptureError {
+    fn from(err: PlayStreamError) -> Self {
+        CaptureError::PlayStream(err)
+    }
+}
+
+impl From<PauseStreamError> for CaptureError {
+    fn from(err: PauseStreamError) -> Self {
+        CaptureError::PauseStream(err)
+    }
+}
+
+impl From<io::Error> for CaptureError {
+    fn from(err: io::Error) -> Self {
+        CaptureError::Io(err)
+    }
+}
+
+impl From<symphonia::core::errors::Error> for CaptureError {
+    fn from(err: symphonia::core::errors::Error) -> Self {
+        CaptureError::Decode(err)
+    }
+}
+
+#[cfg(feature = "jack")]
+impl From<jack::Error> for CaptureError {
+    fn from(err: jack::Error) -> Self {
+        CaptureError::Jack(err)
+    }
+}
@@ -0,0 +1,546 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::{Time, TimeBase};
+
+use super::{
+    spawn_paced, AudioSource, CaptureError, EventSender, Playback, Result, SourceEvent,
+    SAMPLE_RING_CAPACITY,
+};
+use crate::sample_ring::SampleRing;
+
+/// Set in [`Transport::seek`] when no seek is pending.
+const NO_SEEK: usize = usize::MAX;
+
+struct Transport {
+    playing: AtomicBool,
+    looping: AtomicBool,
+    /// Frame the playback has reached.
+    position: AtomicUsize,
+    /// Frame to continue from, picked up by the worker on its next chunk.
+    seek: AtomicUsize,
+    /// Set once a non-looping file has played to the end.
+    ended: AtomicBool,
+}
+
+/// Plays back an audio file (WAV, FLAC, OGG/Vorbis, ...) at real-time pace.
+///
+/// Packets are decoded on the playback thread as they are needed, so opening a long file is
+/// quick and never holds more than a packet of decoded audio.
+pub struct FileSource {
+    /// Shared with the worker, so a stopped file can be started again where it was.
+    decoding: Arc<Mutex<Decoding>>,
+    sample_rate: u32,
+    channels: u16,
+    /// Length in frames, if the container says.
+    frames: Option<usize>,
+    transport: Arc<Transport>,
+    running: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+    samples: Arc<SampleRing>,
+    events: Option<EventSender>,
+}
+
+impl FileSource {
+    /// Reads the headers and decodes the first packet, to fail early on files without audio.
+    /// Errors decoding the rest of the file are reported on `events`, if given.
+    pub fn open(path: impl AsRef<Path>, events: Option<EventSender>) -> Result<Self> {
+        let path = path.as_ref();
+        let stream = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|x| x.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let format = symphonia::default::get_probe()
+            .format(
+                &hint,
+                stream,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )?
+            .format;
+
+        let track = format
+            .default_track()
+            .ok_or_else(|| CaptureError::NoAudio(path.to_path_buf()))?;
+        let track_id = track.id;
+        let time_base = track.codec_params.time_base;
+        let frames = track.codec_params.n_frames.map(|x| x as usize);
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())?;
+
+        let mut decoding = Decoding {
+            format,
+            decoder,
+            track_id,
+            time_base,
+            sample_rate: 0,
+            channels: 0,
+            buffer: None,
+            pending: Vec::new(),
+            offset: 0,
+            skip: 0,
+        };
+
+        if !decoding.decode_next()? || decoding.sample_rate == 0 || decoding.channels == 0 {
+            return Err(CaptureError::NoAudio(path.to_path_buf()));
+        }
+
+        let transport = Transport {
+            playing: AtomicBool::new(false),
+            looping: AtomicBool::new(true),
+            position: AtomicUsize::new(0),
+            seek: AtomicUsize::new(NO_SEEK),
+            ended: AtomicBool::new(false),
+        };
+
+        Ok(FileSource {
+            sample_rate: decoding.sample_rate,
+            channels: decoding.channels as u16,
+            decoding: Arc::new(Mutex::new(decoding)),
+            frames,
+            transport: Arc::new(transport),
+            running: Arc::new(AtomicBool::new(false)),
+            worker: None,
+            samples: Arc::new(SampleRing::new(SAMPLE_RING_CAPACITY)),
+            events,
+        })
+    }
+}
+
+/// The demuxer and decoder of a file, along with the decoded packet being played.
+struct Decoding {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    time_base: Option<TimeBase>,
+    sample_rate: u32,
+    channels: usize,
+    buffer: Option<SampleBuffer<f32>>,
+    /// Interleaved samples of the last decoded packet, played from `offset` on.
+    pending: Vec<f32>,
+    offset: usize,
+    /// Samples still to drop after an accurate seek landed before the requested frame.
+    skip: usize,
+}
+
+impl Decoding {
+    /// Decodes the next packet of the track into `pending`, `false` at the end of the file.
+    fn decode_next(&mut self) -> Result<bool> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(DecodeError::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(false)
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(DecodeError::DecodeError(_)) => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            let spec = *decoded.spec();
+            self.sample_rate = spec.rate;
+            self.channels = spec.channels.count();
+
+            if self
+                .buffer
+                .as_ref()
+                .is_none_or(|x| x.capacity() < decoded.capacity())
+            {
+                self.buffer = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+            }
+
+            if let Some(ref mut buffer) = self.buffer {
+                buffer.copy_interleaved_ref(decoded);
+
+                let skip = self.skip.min(buffer.samples().len());
+                self.skip -= skip;
+
+                self.pending.clear();
+                self.pending.extend_from_slice(&buffer.samples()[skip..]);
+                self.offset = 0;
+            }
+
+            return Ok(true);
+        }
+    }
+
+    /// Moves to `frame`, `false` if the format can't seek there.
+    /// Playback carries on where it was if it can't.
+    fn seek(&mut self, frame: usize) -> bool {
+        let time = Time::from(frame as f64 / self.sample_rate.max(1) as f64);
+        let seeked = self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::Time {
+                time,
+                track_id: Some(self.track_id),
+            },
+        );
+
+        let Ok(seeked) = seeked else {
+            return false;
+        };
+
+        self.decoder.reset();
+        self.pending.clear();
+        self.offset = 0;
+
+        let early = seeked.required_ts.saturating_sub(seeked.actual_ts);
+        let early = match self.time_base {
+            Some(time_base) => {
+                let time = time_base.calc_time(early);
+                ((time.seconds as f64 + time.frac) * self.sample_rate as f64) as usize
+            }
+            None => early as usize,
+        };
+
+        self.skip = early * self.channels;
+        true
+    }
+
+    /// Copies as much of the pending packet into `buffer` as fits.
+    fn take(&mut self, buffer: &mut [f32]) -> usize {
+        let len = buffer.len().min(self.pending.len() - self.offset);
+
+        buffer[..len].copy_from_slice(&self.pending[self.offset..self.offset + len]);
+        self.offset += len;
+
+        len
+    }
+
+    fn is_drained(&self) -> bool {
+        self.offset >= self.pending.len()
+    }
+}
+
+/// Plays the next chunk of the file into `buffer` the way the transport says, returning how many
+/// samples are valid. Playback stops at the end of a file that doesn't loop, or on an error.
+fn fill(
+    decoding: &mut Decoding,
+    transport: &Transport,
+    channels: usize,
+    buffer: &mut [f32],
+) -> (usize, Result<()>) {
+    let seek = transport.seek.swap(NO_SEEK, Ordering::AcqRel);
+
+    if seek != NO_SEEK && decoding.seek(seek) {
+        transport.position.store(seek, Ordering::Release);
+        transport.ended.store(false, Ordering::Release);
+    }
+
+    if !transport.playing.load(Ordering::Acquire) {
+        return (0, Ok(()));
+    }
+
+    let mut written = 0;
+    let mut result = Ok(());
+    // Guards against spinning on a file that ends without producing any audio.
+    let mut rewound = false;
+
+    while written < buffer.len() {
+        if decoding.is_drained() {
+            match decoding.decode_next() {
+                Ok(true) => continue,
+                Ok(false) if transport.looping.load(Ordering::Acquire) && !rewound => {
+                    rewound = true;
+
+                    if decoding.seek(0) {
+                        transport.position.store(0, Ordering::Release);
+                        continue;
+                    }
+                }
+                Ok(false) => {}
+                Err(err) => result = Err(err),
+            }
+
+            transport.playing.store(false, Ordering::Release);
+            transport.ended.store(true, Ordering::Release);
+            break;
+        }
+
+        let len = decoding.take(&mut buffer[written..]);
+        written += len;
+
+        if len > 0 {
+            rewound = false;
+        }
+    }
+
+    transport
+        .position
+        .fetch_add(written / channels, Ordering::AcqRel);
+
+    (written, result)
+}
+
+impl AudioSource for FileSource {
+    fn start(&mut self) -> Result<()> {
+        self.transport.playing.store(true, Ordering::Release);
+
+        if self.worker.is_some() {
+            return Ok(());
+        }
+
+        let decoding = Arc::clone(&self.decoding);
+        let channels = self.channels as usize;
+        let transport = Arc::clone(&self.transport);
+        let mut events = self.events.clone();
+
+        self.running.store(true, Ordering::Release);
+        self.worker = Some(spawn_paced(
+            self.sample_rate,
+            self.channels,
+            Arc::clone(&self.samples),
+            Arc::clone(&self.running),
+            move |buffer| {
+                let Ok(mut decoding) = decoding.lock() else {
+                    return 0;
+                };
+
+                let (written, result) = fill(&mut decoding, &transport, channels, buffer);
+
+                if let (Err(err), Some(events)) = (result, &mut events) {
+                    events.send(SourceEvent::StreamLost(err.to_string()));
+                }
+
+                written
+            },
+        ));
+
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.running.store(false, Ordering::Release);
+
+        if let Some(worker) = self.worker.take() {
+            worker
+                .join()
+                .map_err(|_| CaptureError::WorkerPanicked("file playback"))?;
+        }
+
+        Ok(())
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn samples(&self) -> Arc<SampleRing> {
+        Arc::clone(&self.samples)
+    }
+
+    fn playback(&mut self) -> Option<&mut dyn Playback> {
+        Some(self)
+    }
+}
+
+impl Playback for FileSource {
+    fn toggle_playing(&mut self) {
+        let playing = !self.transport.playing.load(Ordering::Acquire);
+
+        if playing && self.transport.ended.load(Ordering::Acquire) {
+            self.transport.seek.store(0, Ordering::Release);
+        }
+
+        self.transport.playing.store(playing, Ordering::Release);
+    }
+
+    fn toggle_looping(&mut self) {
+        self.transport.looping.fetch_xor(true, Ordering::AcqRel);
+    }
+
+    fn seek_by(&mut self, offset: f32) {
+        let offset = (offset * self.sample_rate as f32) as isize;
+        let position = match self.transport.seek.load(Ordering::Acquire) {
+            NO_SEEK => self.transport.position.load(Ordering::Acquire),
+            seek => seek,
+        } as isize;
+        let end = self.frames.map_or(isize::MAX, |x| x as isize);
+
+        self.transport.seek.store(
+            (position + offset).clamp(0, end) as usize,
+            Ordering::Release,
+        );
+    }
+}
+
+impl Drop for FileSource {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    /// Writes a mono 16-bit WAV holding `samples` to a temporary file.
+    fn write_wav(name: &str, sample_rate: u32, samples: &[i16]) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("stream-intro-{}-{name}.wav", std::process::id()));
+        let data_len = samples.len() as u32 * 2;
+
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+
+        for sample in samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        std::fs::write(&path, wav).unwrap();
+        path
+    }
+
+    #[test]
+    fn plays_file_from_the_start() {
+        let samples = (0..4000).map(|x| (x * 8) as i16).collect::<Vec<_>>();
+        let path = write_wav("start", 8000, &samples);
+
+        let mut source = FileSource::open(&path, None).unwrap();
+        assert_eq!(source.sample_rate(), 8000);
+        assert_eq!(source.channels(), 1);
+
+        let ring = source.samples();
+        source.start().unwrap();
+        while ring.written() < 800 {
+            thread::yield_now();
+        }
+        source.stop().unwrap();
+
+        let written = ring.written();
+
+        let mut played = Vec::new();
+        ring.snapshot(&mut played, written);
+
+        for (played, expected) in played.iter().zip(&samples) {
+            assert!((played - *expected as f32 / 32768.).abs() < 1e-4);
+        }
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    /// Plays `chunks` chunks of `source` on this thread, the way the worker would.
+    fn play(source: &FileSource, chunks: usize) -> Vec<f32> {
+        let mut decoding = source.decoding.lock().unwrap();
+        let mut buffer = [0.; 80];
+        let mut played = Vec::new();
+
+        for _ in 0..chunks {
+            let (written, result) = fill(&mut decoding, &source.transport, 1, &mut buffer);
+            result.unwrap();
+            played.extend_from_slice(&buffer[..written]);
+        }
+
+        played
+    }
+
+    #[test]
+    fn seeks_before_playing() {
+        let samples = (0..4000).map(|x| (x * 8) as i16).collect::<Vec<_>>();
+        let path = write_wav("seek", 8000, &samples);
+
+        let mut source = FileSource::open(&path, None).unwrap();
+        source.seek_by(0.25);
+        source.toggle_playing();
+        let played = play(&source, 1);
+
+        assert_eq!(played.len(), 80);
+        assert!((played[0] - samples[2000] as f32 / 32768.).abs() < 1e-4);
+        assert_eq!(source.transport.position.load(Ordering::Acquire), 2080);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn failed_seek_keeps_playing_from_where_it_was() {
+        let samples = (0..4000).map(|x| (x * 8) as i16).collect::<Vec<_>>();
+        let path = write_wav("bad-seek", 8000, &samples);
+
+        let mut source = FileSource::open(&path, None).unwrap();
+        source.toggle_playing();
+        play(&source, 1);
+
+        assert!(!source.decoding.lock().unwrap().seek(usize::MAX / 2));
+
+        let played = play(&source, 1);
+        assert!((played[0] - samples[80] as f32 / 32768.).abs() < 1e-4);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn stops_at_the_end_without_looping() {
+        let path = write_wav("end", 8000, &[1000; 400]);
+
+        let mut source = FileSource::open(&path, None).unwrap();
+        source.toggle_looping();
+        source.toggle_playing();
+
+        assert_eq!(play(&source, 10).len(), 400);
+        assert!(source.transport.ended.load(Ordering::Acquire));
+        assert!(!source.transport.playing.load(Ordering::Acquire));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn loops_back_to_the_start() {
+        let path = write_wav("loop", 8000, &[1000; 100]);
+
+        let mut source = FileSource::open(&path, None).unwrap();
+        source.toggle_playing();
+
+        assert_eq!(play(&source, 5).len(), 400);
+        assert!(!source.transport.ended.load(Ordering::Acquire));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn rejects_files_without_audio() {
+        let path = write_wav("empty", 8000, &[]);
+
+        assert!(FileSource::open(&path, None).is_err());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,294 @@
+use std::f32::consts::TAU;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use serde::{Deserialize, Serialize};
+
+use super::{spawn_paced, AudioSource, CaptureError, Result, SAMPLE_RING_CAPACITY};
+use crate::sample_ring::SampleRing;
+
+const CLICK_LENGTH: f32 = 0.01;
+const CLICK_FREQUENCY: f32 = 2000.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Signal {
+    Sine,
+    Sweep,
+    Noise,
+    Square,
+    Click,
+}
+
+impl Signal {
+    pub const ALL: [Signal; 5] = [
+        Signal::Sine,
+        Signal::Sweep,
+        Signal::Noise,
+        Signal::Square,
+        Signal::Click,
+    ];
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Signal::Sine => "Sine",
+            Signal::Sweep => "Sweep",
+            Signal::Noise => "Noise",
+            Signal::Square => "Square",
+            Signal::Click => "Click Track",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorConfig {
+    pub signal: Signal,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Tone frequency in Hz.
+    pub frequency: f32,
+    pub amplitude: f32,
+    /// Clicks per minute of [`Signal::Click`].
+    pub bpm: f32,
+    /// Start and end frequency in Hz of the logarithmic sweep.
+    pub sweep_range: (f32, f32),
+    /// Seconds taken to sweep from start to end before starting over.
+    pub sweep_duration: f32,
+    pub seed: u64,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            signal: Signal::Sine,
+            sample_rate: 48000,
+            channels: 2,
+            frequency: 220.,
+            amplitude: 0.5,
+            bpm: 120.,
+            sweep_range: (20., 20000.),
+            sweep_duration: 10.,
+            seed: 0x5eed,
+        }
+    }
+}
+
+/// Deterministic signal generator; the same config always yields the same samples.
+pub struct Generator {
+    config: GeneratorConfig,
+    frame: u64,
+    phase: f32,
+    rng: u64,
+}
+
+impl Generator {
+    pub fn new(config: GeneratorConfig) -> Self {
+        Generator {
+            config,
+            frame: 0,
+            phase: 0.,
+            // xorshift gets stuck on zero
+            rng: config.seed.max(1),
+        }
+    }
+
+    /// Fills `buffer` with interleaved frames, writing the same value to every channel.
+    pub fn fill(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_mut(self.config.channels.max(1) as usize) {
+            frame.fill(self.next_sample());
+        }
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        let GeneratorConfig {
+            signal,
+            sample_rate,
+            frequency,
+            amplitude,
+            bpm,
+            sweep_range: (sweep_start, sweep_end),
+            sweep_duration,
+            ..
+        } = self.config;
+        let time = self.frame as f64 / sample_rate as f64;
+
+        let value = match signal {
+            Signal::Sine => self.advance(frequency).sin(),
+            Signal::Sweep => {
+                let progress = (time / sweep_duration as f64).fract() as f32;
+                let frequency = sweep_start * (sweep_end / sweep_start).powf(progress);
+
+                self.advance(frequency).sin()
+            }
+            Signal::Noise => self.next_random(),
+            Signal::Square => {
+                if self.advance(frequency) < TAU / 2. {
+                    1.
+                } else {
+                    -1.
+                }
+            }
+            Signal::Click => {
+                let since_click = (time % (60. / bpm as f64)) as f32;
+
+                if since_click < CLICK_LENGTH {
+                    (TAU * CLICK_FREQUENCY * since_click).sin() * (1. - since_click / CLICK_LENGTH)
+                } else {
+                    0.
+                }
+            }
+        };
+
+        self.frame += 1;
+
+        value * amplitude
+    }
+
+    /// Steps the oscillator phase forward by one sample at `frequency` and returns the phase
+    /// before stepping.
+    fn advance(&mut self, frequency: f32) -> f32 {
+        let phase = self.phase;
+        self.phase = (self.phase + TAU * frequency / self.config.sample_rate as f32) % TAU;
+
+        phase
+    }
+
+    fn next_random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+
+        (self.rng >> 40) as f32 / (1u64 << 23) as f32 - 1.
+    }
+}
+
+/// Feeds a [`Generator`] into the visualizer at real-time pace.
+pub struct GeneratorSource {
+    config: GeneratorConfig,
+    running: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+    samples: Arc<SampleRing>,
+}
+
+impl GeneratorSource {
+    pub fn new(config: GeneratorConfig) -> Self {
+        GeneratorSource {
+            config,
+            running: Arc::new(AtomicBool::new(false)),
+            worker: None,
+            samples: Arc::new(SampleRing::new(SAMPLE_RING_CAPACITY)),
+        }
+    }
+}
+
+impl AudioSource for GeneratorSource {
+    fn start(&mut self) -> Result<()> {
+        if self.worker.is_some() {
+            return Ok(());
+        }
+
+        let mut generator = Generator::new(self.config);
+
+        self.running.store(true, Ordering::Release);
+        self.worker = Some(spawn_paced(
+            self.config.sample_rate,
+            self.config.channels,
+            Arc::clone(&self.samples),
+            Arc::clone(&self.running),
+            move |buffer| {
+                generator.fill(buffer);
+                buffer.len()
+            },
+        ));
+
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.running.store(false, Ordering::Release);
+
+        if let Some(worker) = self.worker.take() {
+            worker
+                .join()
+                .map_err(|_| CaptureError::WorkerPanicked("signal generator"))?;
+        }
+
+        Ok(())
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.config.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.config.channels
+    }
+
+    fn samples(&self) -> Arc<SampleRing> {
+        Arc::clone(&self.samples)
+    }
+}
+
+impl Drop for GeneratorSource {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(config: GeneratorConfig, len: usize) -> Vec<f32> {
+        let mut buffer = vec![0.; len];
+        Generator::new(config).fill(&mut buffer);
+        buffer
+    }
+
+    #[test]
+    fn same_seed_gives_same_samples() {
+        for signal in Signal::ALL {
+            let config = GeneratorConfig {
+                signal,
+                seed: 42,
+                ..GeneratorConfig::default()
+            };
+
+            assert_eq!(generate(config, 4800), generate(config, 4800), "{signal}");
+        }
+    }
+
+    #[test]
+    fn different_seeds_give_different_noise() {
+        let config = GeneratorConfig {
+            signal: Signal::Noise,
+            ..GeneratorConfig::default()
+        };
+
+        assert_ne!(
+            generate(GeneratorConfig { seed: 1, ..config }, 4800),
+            generate(GeneratorConfig { seed: 2, ..config }, 4800)
+        );
+    }
+
+    #[test]
+    fn clicks_follow_the_bpm() {
+        let config = GeneratorConfig {
+            signal: Signal::Click,
+            sample_rate: 1000,
+            channels: 1,
+            bpm: 120.,
+            ..GeneratorConfig::default()
+        };
+        let samples = generate(config, 2000);
+
+        // One click every 500 ms, silence in between.
+        assert!(samples[..10].iter().any(|x| *x != 0.));
+        assert!(samples[10..500].iter().all(|x| *x == 0.));
+        assert!(samples[500..510].iter().any(|x| *x != 0.));
+    }
+}
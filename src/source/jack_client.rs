@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use jack::{
+    AsyncClient, AudioIn, Client, ClientOptions, ClientStatus, Control, Port, ProcessScope,
+};
+
+use super::{AudioSource, CaptureError, EventSender, Result, SourceEvent, SAMPLE_RING_CAPACITY};
+use crate::sample_ring::SampleRing;
+
+/// Upper bound on input ports, so the process callback can gather buffers without allocating.
+const MAX_JACK_PORTS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JackConfig {
+    pub client_name: String,
+    /// Short names of the input ports, one per channel.
+    pub ports: Vec<String>,
+}
+
+impl Default for JackConfig {
+    fn default() -> Self {
+        JackConfig {
+            client_name: "stream-intro".to_string(),
+            ports: vec!["in_l".to_string(), "in_r".to_string()],
+        }
+    }
+}
+
+struct Notifications {
+    events: Option<EventSender>,
+}
+
+impl jack::NotificationHandler for Notifications {
+    fn shutdown(&mut self, _status: ClientStatus, reason: &str) {
+        if let Some(ref mut events) = self.events {
+            events.send(SourceEvent::StreamLost(format!(
+                "JACK server shut down: {reason}"
+            )));
+        }
+    }
+}
+
+struct Process {
+    ports: Vec<Port<AudioIn>>,
+    samples: Arc<SampleRing>,
+}
+
+impl jack::ProcessHandler for Process {
+    fn process(&mut self, _: &Client, process_scope: &ProcessScope) -> Control {
+        let mut buffers: [&[f32]; MAX_JACK_PORTS] = [&[]; MAX_JACK_PORTS];
+
+        for (buffer, port) in buffers.iter_mut().zip(&self.ports) {
+            *buffer = port.as_slice(process_scope);
+        }
+
+        let channels = self.ports.len();
+        let frames = process_scope.n_frames() as usize;
+
+        // Interleaves the port buffers frame by frame.
+        self.samples
+            .push_iter((0..frames * channels).map(|i| buffers[i % channels][i / channels]));
+
+        Control::Continue
+    }
+}
+
+enum ClientState {
+    Inactive(Client, Notifications, Process),
+    Active(AsyncClient<Notifications, Process>),
+    /// Only seen if (de)activation failed and JACK kept the client.
+    Gone,
+}
+
+/// Registers as a named JACK client whose input ports can be patched to any bus.
+pub struct JackSource {
+    client: ClientState,
+    sample_rate: u32,
+    channels: u16,
+    samples: Arc<SampleRing>,
+}
+
+impl JackSource {
+    pub fn new(config: &JackConfig, events: Option<EventSender>) -> Result<Self> {
+        if config.ports.is_empty() {
+            return Err(CaptureError::NoPorts);
+        }
+
+        if config.ports.len() > MAX_JACK_PORTS {
+            return Err(CaptureError::TooManyPorts {
+                requested: config.ports.len(),
+                max: MAX_JACK_PORTS,
+            });
+        }
+
+        let (client, _status) = Client::new(&config.client_name, ClientOptions::NO_START_SERVER)?;
+
+        let ports = config
+            .ports
+            .iter()
+            .map(|name| client.register_port(name, AudioIn))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let samples = Arc::new(SampleRing::new(SAMPLE_RING_CAPACITY));
+
+        Ok(JackSource {
+            sample_rate: client.sample_rate() as u32,
+            channels: ports.len() as u16,
+            client: ClientState::Inactive(
+                client,
+                Notifications { events },
+                Process {
+                    ports,
+                    samples: Arc::clone(&samples),
+                },
+            ),
+            samples,
+        })
+    }
+}
+
+impl AudioSource for JackSource {
+    fn start(&mut self) -> Result<()> {
+        self.client = match std::mem::replace(&mut self.client, ClientState::Gone) {
+            ClientState::Inactive(client, notifications, process) => {
+                ClientState::Active(client.activate_async(notifications, process)?)
+            }
+            client => client,
+        };
+
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.client = match std::mem::replace(&mut self.client, ClientState::Gone) {
+            ClientState::Active(client) => {
+                let (client, notifications, process) = client.deactivate()?;
+
+                ClientState::Inactive(client, notifications, process)
+            }
+            client => client,
+        };
+
+        Ok(())
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn samples(&self) -> Arc<SampleRing> {
+        Arc::clone(&self.samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ports(count: usize) -> JackConfig {
+        JackConfig {
+            ports: (0..count).map(|i| format!("in_{i}")).collect(),
+            ..JackConfig::default()
+        }
+    }
+
+    #[test]
+    fn rejects_port_counts_out_of_range_before_connecting() {
+        assert!(matches!(
+            JackSource::new(&ports(0), None),
+            Err(CaptureError::NoPorts)
+        ));
+        assert!(matches!(
+            JackSource::new(&ports(MAX_JACK_PORTS + 1), None),
+            Err(CaptureError::TooManyPorts { requested, .. }) if requested == MAX_JACK_PORTS + 1
+        ));
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use iced::futures::channel::mpsc;
+
+use crate::sample_ring::SampleRing;
+
+mod device;
+mod error;
+mod file;
+mod generator;
+#[cfg(feature = "jack")]
+mod jack_client;
+#[cfg(target_os = "linux")]
+mod monitor;
+
+pub use device::{
+    buffer_sizes, default_capture_config, describe_range, device_exists, list_devices,
+    sample_rates, supported_configs, BufferChoice, CaptureConfig, DeviceId, DeviceInfo, DeviceKind,
+    DeviceSelection, DeviceSource, HostDevices,
+};
+pub use error::CaptureError;
+pub use file::FileSource;
+pub use generator::{GeneratorConfig, GeneratorSource, Signal};
+#[cfg(feature = "jack")]
+pub use jack_client::{JackConfig, JackSource};
+#[cfg(target_os = "linux")]
+pub use monitor::list_monitors;
+
+/// Desktop audio capture through sink monitors is only available on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn list_monitors() -> Vec<DeviceInfo> {
+    Vec::new()
+}
+
+/// Enough for several seconds of stereo audio, so long waveform windows still fit.
+pub const SAMPLE_RING_CAPACITY: usize = 1 << 20;
+
+const PACED_CHUNK: Duration = Duration::from_millis(10);
+
+pub type Result<T> = std::result::Result<T, CaptureError>;
+
+/// Notifications a running source sends back to the app from its audio thread.
+#[derive(Debug, Clone)]
+pub enum SourceEvent {
+    /// The stream died, usually because the device was unplugged or the sound server restarted.
+    StreamLost(String),
+}
+
+/// Where a source sends its [`SourceEvent`]s, each tagged with the generation the app gave the
+/// source, so events a replaced source still had queued can be told apart from the current one's.
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    generation: u64,
+    sender: mpsc::Sender<(u64, SourceEvent)>,
+}
+
+impl EventSender {
+    pub fn new(generation: u64, sender: mpsc::Sender<(u64, SourceEvent)>) -> Self {
+        EventSender { generation, sender }
+    }
+
+    /// Never blocks the audio thread; a full channel drops the event.
+    pub fn send(&mut self, event: SourceEvent) {
+        let _ = self.sender.try_send((self.generation, event));
+    }
+}
+
+/// Anything that can feed interleaved `f32` samples to the visualizer.
+///
+/// Sources write into their own [`SampleRing`] from whatever thread they run on; the UI only ever
+/// reads from [`AudioSource::samples`].
+pub trait AudioSource {
+    fn start(&mut self) -> Result<()>;
+
+    fn stop(&mut self) -> Result<()>;
+
+    fn sample_rate(&self) -> u32;
+
+    fn channels(&self) -> u16;
+
+    fn samples(&self) -> Arc<SampleRing>;
+
+    /// Transport controls, for sources that play back something seekable.
+    fn playback(&mut self) -> Option<&mut dyn Playback> {
+        None
+    }
+}
+
+pub trait Playback {
+    fn toggle_playing(&mut self);
+
+    fn toggle_looping(&mut self);
+
+    /// Moves the play head by `offset` seconds, clamped to the length of the media.
+    fn seek_by(&mut self, offset: f32);
+}
+
+/// Runs `fill` on a background thread in real-time sized chunks, pushing whatever it produces
+/// into `samples`, until `running` is cleared.
+///
+/// `fill` is handed a chunk-sized buffer and returns how many samples of it are valid.
+fn spawn_paced(
+    sample_rate: u32,
+    channels: u16,
+    samples: Arc<SampleRing>,
+    running: Arc<AtomicBool>,
+    mut fill: impl FnMut(&mut [f32]) -> usize + Send + 'static,
+) -> JoinHandle<()> {
+    let frames = (sample_rate as f32 * PACED_CHUNK.as_secs_f32()).ceil() as usize;
+    let mut buffer = vec![0.; frames.max(1) * channels as usize];
+
+    thread::spawn(move || {
+        let mut deadline = Instant::now();
+
+        while running.load(Ordering::Acquire) {
+            let len = fill(&mut buffer);
+            samples.push_slice(&buffer[..len]);
+
+            deadline += PACED_CHUNK;
+            match deadline.checked_duration_since(Instant::now()) {
+                Some(wait) => thread::sleep(wait),
+                None => deadline = Instant::now(),
+            }
+        }
+    })
+}
@@ -0,0 +1,416 @@
+use std::io::{self, Read};
+use std::process::{Child, ChildStderr, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use cpal::{
+    BufferSize, HostId, SampleFormat, SampleRate, StreamConfig, SupportedBufferSize,
+    SupportedStreamConfigRange,
+};
+use once_cell::sync::Lazy;
+
+use super::{
+    BufferChoice, CaptureConfig, CaptureError, DeviceId, DeviceInfo, DeviceKind, EventSender,
+    Result, SourceEvent,
+};
+use crate::sample_ring::SampleRing;
+
+/// Latency asked of the sound server when no buffer size was picked.
+const DEFAULT_LATENCY: Duration = Duration::from_millis(20);
+/// Read from `parec` per call; whole frames are pushed, the rest waits for the next read.
+const READ_CHUNK: usize = 16 * 1024;
+
+/// Sinks found by the last listing, so reopening a monitor doesn't have to run `pactl` again.
+static KNOWN_SINKS: Lazy<Mutex<Vec<Sink>>> = Lazy::new(Mutex::default);
+
+#[derive(Debug, Clone)]
+struct Sink {
+    name: String,
+    description: String,
+    monitor: String,
+    channels: u16,
+    sample_rate: u32,
+}
+
+/// Lists the `.monitor` source of every PulseAudio/PipeWire sink as "Desktop audio" devices.
+///
+/// This shells out to `pactl`, so it comes back empty when it isn't installed or no sound
+/// server is running.
+pub fn list_monitors() -> Vec<DeviceInfo> {
+    let default_sink = pactl(&["get-default-sink"]).map(|x| x.trim().to_string());
+    let sinks = pactl(&["list", "sinks"])
+        .map(|x| parse_sinks(&x))
+        .unwrap_or_default();
+
+    let monitors = sinks
+        .iter()
+        .map(|sink| DeviceInfo {
+            label: format!("Desktop audio ({})", sink.description),
+            is_default: default_sink.as_ref() == Some(&sink.name),
+            id: DeviceId {
+                host: HostId::Alsa,
+                kind: DeviceKind::Monitor,
+                name: sink.monitor.clone(),
+            },
+        })
+        .collect();
+
+    if let Ok(mut known) = KNOWN_SINKS.lock() {
+        *known = sinks;
+    }
+
+    monitors
+}
+
+/// Whether the monitor is still there. Always lists the sinks again, so it runs `pactl`.
+pub fn monitor_exists(id: &DeviceId) -> bool {
+    list_monitors().iter().any(|x| x.id == *id)
+}
+
+/// The sink the monitor belongs to, listing the sinks again only when the last listing didn't
+/// have it.
+fn find_sink(id: &DeviceId) -> Result<Sink> {
+    let known = || {
+        KNOWN_SINKS
+            .lock()
+            .ok()?
+            .iter()
+            .find(|x| x.monitor == id.name)
+            .cloned()
+    };
+
+    known()
+        .or_else(|| {
+            list_monitors();
+            known()
+        })
+        .ok_or_else(|| CaptureError::DeviceNotFound(id.name.clone()))
+}
+
+/// Records in the sink's own format, as float so nothing needs converting.
+pub fn capture_config(id: &DeviceId) -> Result<CaptureConfig> {
+    let sink = find_sink(id)?;
+
+    Ok(CaptureConfig {
+        channels: sink.channels,
+        sample_rate: sink.sample_rate,
+        sample_format: SampleFormat::F32,
+        buffer_size: BufferChoice::Default,
+    })
+}
+
+/// The sound server converts to any rate, but the sink's channel layout is kept.
+pub fn supported_configs(id: &DeviceId) -> Result<Vec<SupportedStreamConfigRange>> {
+    let config = capture_config(id)?;
+
+    Ok(vec![SupportedStreamConfigRange::new(
+        config.channels,
+        SampleRate(8000),
+        SampleRate(192000),
+        SupportedBufferSize::Unknown,
+        SampleFormat::F32,
+    )])
+}
+
+fn pactl(args: &[&str]) -> Option<String> {
+    let output = Command::new("pactl")
+        .args(args)
+        // Field names in `pactl list` are translated otherwise.
+        .env("LC_ALL", "C")
+        .output()
+        .ok()
+        .filter(|x| x.status.success())?;
+
+    String::from_utf8(output.stdout).ok()
+}
+
+fn parse_sinks(output: &str) -> Vec<Sink> {
+    let mut sinks = Vec::new();
+    let mut sink: Option<Sink> = None;
+
+    for line in output.lines() {
+        if line.starts_with("Sink #") {
+            sinks.extend(sink.take());
+            sink = Some(Sink {
+                name: String::new(),
+                description: String::new(),
+                monitor: String::new(),
+                channels: 2,
+                sample_rate: 48000,
+            });
+
+            continue;
+        }
+
+        let Some(ref mut sink) = sink else {
+            continue;
+        };
+
+        let line = line.trim();
+
+        if let Some(name) = line.strip_prefix("Name: ") {
+            sink.name = name.to_string();
+        } else if let Some(description) = line.strip_prefix("Description: ") {
+            sink.description = description.to_string();
+        } else if let Some(monitor) = line.strip_prefix("Monitor Source: ") {
+            sink.monitor = monitor.to_string();
+        } else if let Some(spec) = line.strip_prefix("Sample Specification: ") {
+            // e.g. `s32le 2ch 48000Hz`
+            for field in spec.split_whitespace() {
+                if let Some(Ok(channels)) = field.strip_suffix("ch").map(str::parse) {
+                    sink.channels = channels;
+                } else if let Some(Ok(sample_rate)) = field.strip_suffix("Hz").map(str::parse) {
+                    sink.sample_rate = sample_rate;
+                }
+            }
+        }
+    }
+
+    sinks.extend(sink);
+    sinks.retain(|x| !x.monitor.is_empty());
+
+    for sink in &mut sinks {
+        if sink.description.is_empty() {
+            sink.description = sink.name.clone();
+        }
+    }
+
+    sinks
+}
+
+/// A running `parec`, which is told the source to record on its command line.
+pub struct Recording {
+    child: Child,
+    reader: Option<JoinHandle<()>>,
+    stopping: Arc<AtomicBool>,
+}
+
+/// Starts recording the monitor into `samples`. If the recorder exits by itself, e.g. because
+/// the sink was removed, that counts as an error and the stream is reported lost on `events`.
+pub fn record(
+    id: &DeviceId,
+    config: &StreamConfig,
+    samples: Arc<SampleRing>,
+    errors: Arc<AtomicUsize>,
+    events: Option<EventSender>,
+) -> Result<Recording> {
+    let latency = match config.buffer_size {
+        BufferSize::Fixed(frames) => {
+            Duration::from_secs_f32(frames as f32 / config.sample_rate.0.max(1) as f32)
+        }
+        BufferSize::Default => DEFAULT_LATENCY,
+    };
+
+    let mut child = Command::new("parec")
+        .arg(format!("--device={}", id.name))
+        .arg("--format=float32le")
+        .arg(format!("--rate={}", config.sample_rate.0))
+        .arg(format!("--channels={}", config.channels))
+        .arg(format!("--latency-msec={}", latency.as_millis().max(1)))
+        .arg("--raw")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(CaptureError::Parec)?;
+
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return Err(CaptureError::Parec(io::ErrorKind::BrokenPipe.into()));
+    };
+
+    let stopping = Arc::new(AtomicBool::new(false));
+    let frame_len = config.channels.max(1) as usize * 4;
+    let reader = {
+        let stopping = Arc::clone(&stopping);
+
+        thread::spawn(move || {
+            read_samples(stdout, frame_len, &samples);
+
+            if !stopping.load(Ordering::Acquire) {
+                errors.fetch_add(1, Ordering::Relaxed);
+
+                if let Some(mut events) = events {
+                    events.send(SourceEvent::StreamLost(exit_reason(stderr)));
+                }
+            }
+        })
+    };
+
+    Ok(Recording {
+        child,
+        reader: Some(reader),
+        stopping,
+    })
+}
+
+/// Pushes whole frames of little endian floats into `samples` until `parec` stops writing.
+fn read_samples(mut stdout: impl Read, frame_len: usize, samples: &SampleRing) {
+    let mut buffer = vec![0; READ_CHUNK.max(frame_len)];
+    let mut filled = 0;
+
+    loop {
+        match stdout.read(&mut buffer[filled..]) {
+            Ok(0) => return,
+            Ok(len) => filled += len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return,
+        }
+
+        let whole = filled - filled % frame_len;
+        samples.push_iter(
+            buffer[..whole]
+                .chunks_exact(4)
+                .map(|x| f32::from_le_bytes([x[0], x[1], x[2], x[3]])),
+        );
+
+        buffer.copy_within(whole..filled, 0);
+        filled -= whole;
+    }
+}
+
+/// What `parec` said before it exited, which names the problem, e.g. "No such entity".
+fn exit_reason(mut stderr: ChildStderr) -> String {
+    let mut output = String::new();
+    let _ = stderr.read_to_string(&mut output);
+
+    output
+        .lines()
+        .last()
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .unwrap_or("parec exited")
+        .to_string()
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Release);
+
+        if let Err(err) = self.child.kill() {
+            eprintln!("failed to stop parec: {err}");
+        }
+        let _ = self.child.wait();
+
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trimmed `pactl list sinks` output with a hardware sink and a null sink created by
+    /// `pactl load-module module-null-sink sink_name=intro_test`.
+    const LIST_SINKS: &str = "\
+Sink #46
+\tState: RUNNING
+\tName: alsa_output.pci-0000_00_1f.3.analog-stereo
+\tDescription: Built-in Audio Analog Stereo
+\tDriver: PipeWire
+\tSample Specification: s32le 2ch 48000Hz
+\tMonitor Source: alsa_output.pci-0000_00_1f.3.analog-stereo.monitor
+\tProperties:
+\t\tdevice.description = \"Built-in Audio\"
+Sink #812
+\tState: SUSPENDED
+\tName: intro_test
+\tDescription: Null Output
+\tDriver: PipeWire
+\tMonitor Source: intro_test.monitor
+";
+
+    #[test]
+    fn parses_sinks_with_monitors() {
+        let sinks = parse_sinks(LIST_SINKS);
+
+        assert_eq!(sinks.len(), 2);
+        assert_eq!(sinks[0].name, "alsa_output.pci-0000_00_1f.3.analog-stereo");
+        assert_eq!(sinks[0].description, "Built-in Audio Analog Stereo");
+        assert_eq!(
+            sinks[0].monitor,
+            "alsa_output.pci-0000_00_1f.3.analog-stereo.monitor"
+        );
+        assert_eq!((sinks[0].channels, sinks[0].sample_rate), (2, 48000));
+        assert_eq!(sinks[1].name, "intro_test");
+        assert_eq!(sinks[1].monitor, "intro_test.monitor");
+    }
+
+    #[test]
+    fn reads_the_sample_specification() {
+        let output = "\
+Sink #3
+\tName: surround
+\tSample Specification: float32le 6ch 96000Hz
+\tMonitor Source: surround.monitor
+";
+
+        let sinks = parse_sinks(output);
+
+        assert_eq!((sinks[0].channels, sinks[0].sample_rate), (6, 96000));
+    }
+
+    /// Hands out a few bytes per read, splitting samples and frames like a pipe can.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            let len = buffer.len().min(self.0.len()).min(3);
+            buffer[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn pushes_whole_frames_only() {
+        let bytes = [0.5f32, -0.5, 0.25, -0.25, 1.]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<_>>();
+        let samples = SampleRing::new(16);
+        let mut out = Vec::new();
+
+        // Stereo, so the last sample is half a frame and stays unread.
+        read_samples(Trickle(&bytes), 8, &samples);
+        samples.snapshot(&mut out, 16);
+
+        assert_eq!(out, [0.5, -0.5, 0.25, -0.25]);
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+        let output = "\
+\tName: before any sink
+garbage
+Sink #1
+\tName: no_monitor
+\tDescription:
+Sink #2
+\tName: null
+\tMonitor Source: null.monitor
+\tMonitor Source
+";
+
+        let sinks = parse_sinks(output);
+
+        assert_eq!(sinks.len(), 1);
+        assert_eq!(sinks[0].monitor, "null.monitor");
+        // Falls back to the name without a description.
+        assert_eq!(sinks[0].description, "null");
+    }
+
+    #[test]
+    fn ignores_short_listing() {
+        // `pactl list short sinks` has no monitor names, so it must not produce any devices.
+        let output = "\
+46\talsa_output.pci-0000_00_1f.3.analog-stereo\tPipeWire\ts32le 2ch 48000Hz\tRUNNING
+812\tintro_test\tPipeWire\tfloat32le 2ch 48000Hz\tSUSPENDED
+";
+
+        assert!(parse_sinks(output).is_empty());
+    }
+}
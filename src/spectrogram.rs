@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use iced::widget::image;
+use iced::Color;
+
+/// Number of FFT frames kept on screen, one per tick.
+pub const SPECTROGRAM_HISTORY: usize = 256;
+pub const SPECTROGRAM_BANDS: usize = 128;
+
+const VIRIDIS: [Color; 5] = [
+    Color::from_rgb(0.267, 0.005, 0.329),
+    Color::from_rgb(0.231, 0.322, 0.545),
+    Color::from_rgb(0.129, 0.569, 0.549),
+    Color::from_rgb(0.369, 0.788, 0.384),
+    Color::from_rgb(0.992, 0.906, 0.145),
+];
+const MAGMA: [Color; 6] = [
+    Color::from_rgb(0., 0., 0.016),
+    Color::from_rgb(0.231, 0.059, 0.439),
+    Color::from_rgb(0.549, 0.161, 0.506),
+    Color::from_rgb(0.871, 0.286, 0.408),
+    Color::from_rgb(0.996, 0.624, 0.427),
+    Color::from_rgb(0.988, 0.992, 0.749),
+];
+const GRAYSCALE: [Color; 2] = [Color::BLACK, Color::WHITE];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colormap {
+    #[default]
+    Viridis,
+    Magma,
+    Grayscale,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 3] = [Colormap::Viridis, Colormap::Magma, Colormap::Grayscale];
+
+    /// Maps a level between 0 and 1 to a color by interpolating between the map's stops.
+    pub fn color(self, level: f32) -> Color {
+        let stops: &[Color] = match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Grayscale => &GRAYSCALE,
+        };
+
+        let position = level.clamp(0., 1.) * (stops.len() - 1) as f32;
+        let index = (position as usize).min(stops.len() - 2);
+        let (from, to) = (stops[index], stops[index + 1]);
+        let t = position - index as f32;
+
+        Color::from_rgb(
+            from.r + (to.r - from.r) * t,
+            from.g + (to.g - from.g) * t,
+            from.b + (to.b - from.b) * t,
+        )
+    }
+}
+
+impl fmt::Display for Colormap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Colormap::Viridis => "Viridis",
+            Colormap::Magma => "Magma",
+            Colormap::Grayscale => "Grayscale",
+        })
+    }
+}
+
+/// Rolling history of spectrum frames, oldest first, along with an image of it.
+///
+/// The image is updated one column per frame instead of being redrawn, so showing the
+/// spectrogram costs the same however much of it there is.
+#[derive(Debug)]
+pub struct SpectrogramHistory {
+    frames: VecDeque<Vec<f32>>,
+    colormap: Colormap,
+    /// RGBA, one column per frame with the newest on the right and the highest band on top.
+    pixels: Vec<u8>,
+    image: image::Handle,
+}
+
+impl Default for SpectrogramHistory {
+    fn default() -> Self {
+        let mut history = SpectrogramHistory {
+            frames: VecDeque::with_capacity(SPECTROGRAM_HISTORY),
+            colormap: Colormap::default(),
+            pixels: vec![0; SPECTROGRAM_HISTORY * SPECTROGRAM_BANDS * 4],
+            image: image::Handle::from_pixels(0, 0, Vec::new()),
+        };
+        history.repaint();
+
+        history
+    }
+}
+
+impl SpectrogramHistory {
+    pub fn push(&mut self, bands: Vec<f32>) {
+        if self.frames.len() == SPECTROGRAM_HISTORY {
+            self.frames.pop_front();
+        }
+
+        // Scroll everything one column to the left and paint the new frame on the right.
+        for row in self.pixels.chunks_exact_mut(SPECTROGRAM_HISTORY * 4) {
+            row.copy_within(4.., 0);
+        }
+        paint_column(
+            &mut self.pixels,
+            SPECTROGRAM_HISTORY - 1,
+            &bands,
+            self.colormap,
+        );
+
+        self.frames.push_back(bands);
+        self.update_image();
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.repaint();
+    }
+
+    pub fn colormap(&self) -> Colormap {
+        self.colormap
+    }
+
+    pub fn set_colormap(&mut self, colormap: Colormap) {
+        if colormap != self.colormap {
+            self.colormap = colormap;
+            self.repaint();
+        }
+    }
+
+    pub fn image(&self) -> image::Handle {
+        self.image.clone()
+    }
+
+    /// Paints every frame again, e.g. in a new colormap.
+    fn repaint(&mut self) {
+        let background = self.colormap.color(0.).into_rgba8();
+
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&background);
+        }
+
+        let offset = SPECTROGRAM_HISTORY - self.frames.len();
+
+        for (column, bands) in self.frames.iter().enumerate() {
+            paint_column(&mut self.pixels, offset + column, bands, self.colormap);
+        }
+
+        self.update_image();
+    }
+
+    fn update_image(&mut self) {
+        self.image = image::Handle::from_pixels(
+            SPECTROGRAM_HISTORY as u32,
+            SPECTROGRAM_BANDS as u32,
+            self.pixels.clone(),
+        );
+    }
+}
+
+/// Colors column `x` of the image after `bands`, missing bands reading as silence.
+fn paint_column(pixels: &mut [u8], x: usize, bands: &[f32], colormap: Colormap) {
+    for (row, pixel) in pixels
+        .chunks_exact_mut(4)
+        .skip(x)
+        .step_by(SPECTROGRAM_HISTORY)
+        .enumerate()
+    {
+        let level = bands
+            .get(SPECTROGRAM_BANDS - 1 - row)
+            .copied()
+            .unwrap_or(0.);
+
+        pixel.copy_from_slice(&colormap.color(level).into_rgba8());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color_eq(a: Color, b: Color) {
+        let close = |x: f32, y: f32| (x - y).abs() < 1e-5;
+
+        assert!(
+            close(a.r, b.r) && close(a.g, b.g) && close(a.b, b.b),
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn colormaps_hit_their_stops() {
+        assert_color_eq(Colormap::Viridis.color(0.), VIRIDIS[0]);
+        assert_color_eq(Colormap::Viridis.color(0.25), VIRIDIS[1]);
+        assert_color_eq(Colormap::Viridis.color(1.), VIRIDIS[4]);
+        assert_color_eq(Colormap::Magma.color(0.6), MAGMA[3]);
+        assert_color_eq(Colormap::Magma.color(1.), MAGMA[5]);
+    }
+
+    #[test]
+    fn colormaps_interpolate_between_stops() {
+        assert_color_eq(
+            Colormap::Grayscale.color(0.5),
+            Color::from_rgb(0.5, 0.5, 0.5),
+        );
+
+        let halfway = Colormap::Viridis.color(0.125);
+        assert_color_eq(
+            halfway,
+            Color::from_rgb(
+                (VIRIDIS[0].r + VIRIDIS[1].r) / 2.,
+                (VIRIDIS[0].g + VIRIDIS[1].g) / 2.,
+                (VIRIDIS[0].b + VIRIDIS[1].b) / 2.,
+            ),
+        );
+    }
+
+    #[test]
+    fn colormaps_clamp_out_of_range_levels() {
+        for colormap in Colormap::ALL {
+            assert_color_eq(colormap.color(-1.), colormap.color(0.));
+            assert_color_eq(colormap.color(2.), colormap.color(1.));
+            assert_color_eq(colormap.color(f32::INFINITY), colormap.color(1.));
+        }
+    }
+
+    fn pixel(history: &SpectrogramHistory, x: usize, row: usize) -> [u8; 4] {
+        let i = (row * SPECTROGRAM_HISTORY + x) * 4;
+
+        history.pixels[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn scrolls_new_frames_in_from_the_right() {
+        let mut history = SpectrogramHistory::default();
+        let loud = Colormap::default().color(1.).into_rgba8();
+        let quiet = Colormap::default().color(0.).into_rgba8();
+        let last = SPECTROGRAM_HISTORY - 1;
+
+        // Only the lowest band, which is the bottom row.
+        let mut bands = vec![0.; SPECTROGRAM_BANDS];
+        bands[0] = 1.;
+        history.push(bands);
+
+        assert_eq!(pixel(&history, last, SPECTROGRAM_BANDS - 1), loud);
+        assert_eq!(pixel(&history, last, 0), quiet);
+
+        history.push(vec![0.; SPECTROGRAM_BANDS]);
+
+        assert_eq!(pixel(&history, last - 1, SPECTROGRAM_BANDS - 1), loud);
+        assert_eq!(pixel(&history, last, SPECTROGRAM_BANDS - 1), quiet);
+
+        history.set_colormap(Colormap::Grayscale);
+
+        assert_eq!(pixel(&history, last - 1, SPECTROGRAM_BANDS - 1), [255; 4]);
+        assert_eq!(pixel(&history, 0, 0), [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,360 @@
+use std::f32::consts::TAU;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use iced::widget::canvas::{path, stroke::Stroke, Fill, Frame};
+use iced::{Color, Point, Rectangle, Size};
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+use crate::sample_ring::SampleRing;
+use crate::visual::Visual;
+use crate::waveform;
+
+pub const FFT_SIZES: [usize; 5] = [512, 1024, 2048, 4096, 8192];
+
+const LOWEST_FREQUENCY: f32 = 20.;
+const HIGHEST_FREQUENCY: f32 = 20000.;
+const BAR_GAP: f32 = 2.;
+const SPECTRUM_COLOR: Color = Color::BLACK;
+const PEAK_COLOR: Color = Color::from_rgb(0.8, 0., 0.8);
+const PEAK_HEIGHT: f32 = 3.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Window {
+    #[default]
+    Hann,
+    Blackman,
+}
+
+impl Window {
+    pub const ALL: [Window; 2] = [Window::Hann, Window::Blackman];
+
+    fn coefficients(self, len: usize) -> Vec<f32> {
+        let n = (len - 1).max(1) as f32;
+
+        (0..len)
+            .map(|i| {
+                let x = TAU * i as f32 / n;
+
+                match self {
+                    Window::Hann => 0.5 - 0.5 * x.cos(),
+                    Window::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2. * x).cos(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Window::Hann => "Hann",
+            Window::Blackman => "Blackman",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpectrumStyle {
+    #[default]
+    Bars,
+    Curve,
+}
+
+impl SpectrumStyle {
+    pub const ALL: [SpectrumStyle; 2] = [SpectrumStyle::Bars, SpectrumStyle::Curve];
+}
+
+impl fmt::Display for SpectrumStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SpectrumStyle::Bars => "Bars",
+            SpectrumStyle::Curve => "Filled Curve",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrumConfig {
+    pub fft_size: usize,
+    pub window: Window,
+    /// How much of the previous frame is kept when a bin falls, from 0 (none) to 1 (frozen).
+    /// Rising bins always jump straight to the new value.
+    pub smoothing: f32,
+    pub min_db: f32,
+    pub max_db: f32,
+    pub style: SpectrumStyle,
+    pub bands: usize,
+}
+
+impl Default for SpectrumConfig {
+    fn default() -> Self {
+        SpectrumConfig {
+            fft_size: 4096,
+            window: Window::default(),
+            smoothing: 0.8,
+            min_db: -90.,
+            max_db: 0.,
+            style: SpectrumStyle::default(),
+            bands: 64,
+        }
+    }
+}
+
+/// Turns the latest samples of a source into smoothed, log-frequency spectrum bands.
+pub struct SpectrumAnalyzer {
+    config: SpectrumConfig,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    samples: Vec<f32>,
+    buffer: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    /// Level of each FFT bin between `min_db` (0) and `max_db` (1).
+    levels: Vec<f32>,
+    sample_rate: u32,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(config: SpectrumConfig) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(config.fft_size);
+
+        SpectrumAnalyzer {
+            window: config.window.coefficients(config.fft_size),
+            samples: Vec::with_capacity(config.fft_size),
+            buffer: vec![Complex::default(); config.fft_size],
+            scratch: vec![Complex::default(); fft.get_inplace_scratch_len()],
+            levels: vec![0.; config.fft_size / 2],
+            sample_rate: 48000,
+            config,
+            fft,
+        }
+    }
+
+    pub fn config(&self) -> SpectrumConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: SpectrumConfig) {
+        if config.fft_size != self.config.fft_size || config.window != self.config.window {
+            *self = SpectrumAnalyzer::new(config);
+        } else {
+            self.config = config;
+        }
+    }
+
+    /// Runs the FFT over the most recent `fft_size` frames of `ring`, downmixed to mono.
+    pub fn update(&mut self, ring: &SampleRing, channels: u16, sample_rate: u32) {
+        let channels = channels.max(1) as usize;
+        let SpectrumConfig {
+            fft_size,
+            smoothing,
+            min_db,
+            max_db,
+            ..
+        } = self.config;
+
+        ring.snapshot(&mut self.samples, fft_size * channels);
+        self.samples.drain(..self.samples.len() % channels);
+        let mono = waveform::downmix(&self.samples, channels);
+
+        // Left-pad with silence until the ring has filled up.
+        let padding = fft_size - mono.len();
+        for (i, value) in self.buffer.iter_mut().enumerate() {
+            let sample = if i < padding { 0. } else { mono[i - padding] };
+            *value = Complex::new(sample * self.window[i], 0.);
+        }
+
+        self.fft
+            .process_with_scratch(&mut self.buffer, &mut self.scratch);
+        self.sample_rate = sample_rate;
+
+        // Normalise so a full-scale sine reads 0 dB regardless of window and FFT size.
+        let gain = 2. / self.window.iter().sum::<f32>();
+
+        for (level, bin) in self.levels.iter_mut().zip(&self.buffer) {
+            let db = 20. * (bin.norm() * gain).max(1e-10).log10();
+            let new_level = ((db - min_db) / (max_db - min_db)).clamp(0., 1.);
+
+            *level = if new_level >= *level {
+                new_level
+            } else {
+                *level * smoothing + new_level * (1. - smoothing)
+            };
+        }
+    }
+
+    /// Levels for `count` bands spaced logarithmically from 20 Hz to 20 kHz (or Nyquist).
+    pub fn bands(&self, count: usize) -> Vec<f32> {
+        self.band_bins(count)
+            .into_iter()
+            .map(|bins| self.levels[bins].iter().copied().fold(0., f32::max))
+            .collect()
+    }
+
+    /// The FFT bins in each band. Neighbouring bands share the bin on their edge, so narrow
+    /// low bands still get a bin each.
+    fn band_bins(&self, count: usize) -> Vec<RangeInclusive<usize>> {
+        let bin_width = self.sample_rate as f32 / self.config.fft_size as f32;
+        let highest = HIGHEST_FREQUENCY.min(self.sample_rate as f32 / 2.);
+        let ratio = highest / LOWEST_FREQUENCY;
+        let last_bin = self.levels.len().saturating_sub(1);
+
+        (0..count)
+            .map(|i| {
+                let low = LOWEST_FREQUENCY * ratio.powf(i as f32 / count as f32);
+                let high = LOWEST_FREQUENCY * ratio.powf((i + 1) as f32 / count as f32);
+
+                let first = ((low / bin_width).round() as usize).min(last_bin);
+                let last = ((high / bin_width).round() as usize).clamp(first, last_bin);
+
+                first..=last
+            })
+            .collect()
+    }
+}
+
+pub struct Spectrum {
+    pub bands: Vec<f32>,
+    /// Held peak of every band, empty when peak hold is off.
+    pub peaks: Vec<f32>,
+    pub style: SpectrumStyle,
+}
+
+impl Visual for Spectrum {
+    fn draw(&self, frame: &mut Frame) {
+        let bounds = Rectangle::with_size(frame.size());
+
+        if self.bands.is_empty() {
+            return;
+        }
+
+        let band_width = bounds.width / self.bands.len() as f32;
+
+        match self.style {
+            SpectrumStyle::Bars => {
+                for (i, level) in self.bands.iter().enumerate() {
+                    let height = level * bounds.height;
+
+                    frame.fill_rectangle(
+                        Point::new(i as f32 * band_width, bounds.height - height),
+                        Size::new((band_width - BAR_GAP).max(1.), height),
+                        SPECTRUM_COLOR,
+                    );
+                }
+
+                for (i, peak) in self.peaks.iter().enumerate() {
+                    frame.fill_rectangle(
+                        Point::new(
+                            i as f32 * band_width,
+                            bounds.height - peak * bounds.height - PEAK_HEIGHT,
+                        ),
+                        Size::new((band_width - BAR_GAP).max(1.), PEAK_HEIGHT),
+                        PEAK_COLOR,
+                    );
+                }
+            }
+            SpectrumStyle::Curve => {
+                let mut path_builder = path::Builder::new();
+                path_builder.move_to(Point::new(0., bounds.height));
+
+                for (i, level) in self.bands.iter().enumerate() {
+                    path_builder.line_to(Point::new(
+                        (i as f32 + 0.5) * band_width,
+                        bounds.height - level * bounds.height,
+                    ));
+                }
+
+                path_builder.line_to(Point::new(bounds.width, bounds.height));
+                path_builder.close();
+
+                frame.fill(&path_builder.build(), Fill::from(SPECTRUM_COLOR));
+
+                if !self.peaks.is_empty() {
+                    let peaks = path::Path::new(|builder| {
+                        for (i, peak) in self.peaks.iter().enumerate() {
+                            builder.line_to(Point::new(
+                                (i as f32 + 0.5) * band_width,
+                                bounds.height - peak * bounds.height,
+                            ));
+                        }
+                    });
+
+                    frame.stroke(
+                        &peaks,
+                        Stroke::default().with_color(PEAK_COLOR).with_width(2.),
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_scale_sine_reads_zero_db() {
+        let (fft_size, sample_rate, bin) = (4096, 48000, 100);
+        let sine = (0..fft_size)
+            .map(|i| (TAU * (bin * i) as f32 / fft_size as f32).sin())
+            .collect::<Vec<_>>();
+        let ring = SampleRing::new(fft_size);
+        ring.push_slice(&sine);
+
+        for window in Window::ALL {
+            // Headroom above 0 dB, so a reading that's too loud isn't clamped away.
+            let mut analyzer = SpectrumAnalyzer::new(SpectrumConfig {
+                fft_size,
+                window,
+                min_db: -90.,
+                max_db: 10.,
+                ..SpectrumConfig::default()
+            });
+            analyzer.update(&ring, 1, sample_rate);
+
+            let db = analyzer.levels[bin] * 100. - 90.;
+            assert!(db.abs() < 0.1, "{window}: {db} dB");
+        }
+    }
+
+    #[test]
+    fn bands_cover_the_spectrum_in_order() {
+        for (fft_size, sample_rate) in [(512, 8000), (4096, 48000), (8192, 192000)] {
+            let mut analyzer = SpectrumAnalyzer::new(SpectrumConfig {
+                fft_size,
+                ..SpectrumConfig::default()
+            });
+            analyzer.sample_rate = sample_rate;
+
+            for count in [8, 64, 256] {
+                let bins = analyzer.band_bins(count);
+                let bin_width = sample_rate as f32 / fft_size as f32;
+                let highest = HIGHEST_FREQUENCY.min(sample_rate as f32 / 2.);
+
+                assert_eq!(bins.len(), count);
+                assert_eq!(
+                    *bins[0].start(),
+                    (LOWEST_FREQUENCY / bin_width).round() as usize
+                );
+                assert_eq!(
+                    *bins[count - 1].end(),
+                    ((highest / bin_width).round() as usize).min(fft_size / 2 - 1)
+                );
+
+                for band in &bins {
+                    assert!(!band.is_empty());
+                }
+
+                // Each band starts where the previous one ended, without gaps or going back.
+                for pair in bins.windows(2) {
+                    assert_eq!(pair[1].start(), pair[0].end());
+                    assert!(pair[1].end() >= pair[0].end());
+                }
+            }
+        }
+    }
+}
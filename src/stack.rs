@@ -0,0 +1,200 @@
+use iced_native::widget::{self, Tree};
+use iced_native::{
+    event, layout, mouse, overlay, renderer, Clipboard, Element, Event, Layout, Length, Point,
+    Rectangle, Shell, Size, Widget,
+};
+
+/// Draws its layers on top of each other, the first one at the bottom. The first layer decides
+/// the size of the stack, every other layer is laid out inside it.
+pub struct Stack<'a, Message, Renderer> {
+    layers: Vec<Element<'a, Message, Renderer>>,
+}
+
+impl<'a, Message, Renderer> Stack<'a, Message, Renderer> {
+    pub fn new(base: impl Into<Element<'a, Message, Renderer>>) -> Self {
+        Self {
+            layers: vec![base.into()],
+        }
+    }
+
+    pub fn push(mut self, layer: impl Into<Element<'a, Message, Renderer>>) -> Self {
+        self.layers.push(layer.into());
+        self
+    }
+}
+
+impl<'a, Message, Renderer> Widget<Message, Renderer> for Stack<'a, Message, Renderer>
+where
+    Renderer: iced_native::Renderer,
+{
+    fn children(&self) -> Vec<Tree> {
+        self.layers.iter().map(Tree::new).collect()
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&self.layers);
+    }
+
+    fn width(&self) -> Length {
+        self.layers[0].as_widget().width()
+    }
+
+    fn height(&self) -> Length {
+        self.layers[0].as_widget().height()
+    }
+
+    fn layout(&self, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        let base = self.layers[0].as_widget().layout(renderer, limits);
+        let size = base.size();
+        let limits = layout::Limits::new(Size::ZERO, size);
+
+        let mut children = vec![base];
+        children.extend(
+            self.layers[1..]
+                .iter()
+                .map(|layer| layer.as_widget().layout(renderer, &limits)),
+        );
+
+        layout::Node::with_children(size, children)
+    }
+
+    fn on_event(
+        &mut self,
+        state: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+    ) -> event::Status {
+        let layouts: Vec<_> = layout.children().collect();
+
+        // Topmost layer gets the first chance to capture the event.
+        self.layers
+            .iter_mut()
+            .zip(&mut state.children)
+            .zip(layouts)
+            .rev()
+            .fold(
+                event::Status::Ignored,
+                |status, ((layer, state), layout)| {
+                    if status == event::Status::Captured {
+                        return status;
+                    }
+
+                    layer.as_widget_mut().on_event(
+                        state,
+                        event.clone(),
+                        layout,
+                        cursor_position,
+                        renderer,
+                        clipboard,
+                        shell,
+                    )
+                },
+            )
+    }
+
+    fn draw(
+        &self,
+        state: &Tree,
+        renderer: &mut Renderer,
+        theme: &<Renderer as iced_native::Renderer>::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) {
+        for (i, ((layer, state), layout)) in self
+            .layers
+            .iter()
+            .zip(&state.children)
+            .zip(layout.children())
+            .enumerate()
+        {
+            let draw = |renderer: &mut Renderer| {
+                layer.as_widget().draw(
+                    state,
+                    renderer,
+                    theme,
+                    style,
+                    layout,
+                    cursor_position,
+                    viewport,
+                );
+            };
+
+            // Within one render layer images always end up above meshes, so every layer on top
+            // of the base gets its own to keep e.g. a canvas above a background image.
+            if i == 0 {
+                draw(renderer);
+            } else {
+                renderer.with_layer(layout.bounds(), draw);
+            }
+        }
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        state: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+    ) -> Option<overlay::Element<'b, Message, Renderer>> {
+        overlay::from_children(&mut self.layers, state, layout, renderer)
+    }
+
+    fn mouse_interaction(
+        &self,
+        state: &Tree,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.layers
+            .iter()
+            .zip(&state.children)
+            .zip(layout.children())
+            .map(|((layer, state), layout)| {
+                layer.as_widget().mouse_interaction(
+                    state,
+                    layout,
+                    cursor_position,
+                    viewport,
+                    renderer,
+                )
+            })
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn operate(
+        &self,
+        state: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn widget::Operation<Message>,
+    ) {
+        for ((layer, state), layout) in self
+            .layers
+            .iter()
+            .zip(&mut state.children)
+            .zip(layout.children())
+        {
+            layer
+                .as_widget()
+                .operate(state, layout, renderer, operation);
+        }
+    }
+}
+
+impl<'a, Message, Renderer> From<Stack<'a, Message, Renderer>> for Element<'a, Message, Renderer>
+where
+    Renderer: 'a + iced_native::Renderer,
+    Message: 'a,
+{
+    fn from(stack: Stack<'a, Message, Renderer>) -> Self {
+        Element::new(stack)
+    }
+}
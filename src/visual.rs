@@ -0,0 +1,30 @@
+use iced::widget::canvas::{Cache, Cursor, Frame, Geometry, Program};
+use iced::{Rectangle, Theme};
+
+/// Something drawn onto the visualizer canvas.
+pub trait Visual {
+    fn draw(&self, frame: &mut Frame);
+}
+
+/// Canvas program that reuses the geometry in `cache` until the app clears it, so a visual is
+/// only rebuilt when there is something new to show.
+pub struct Cached<'a, V> {
+    pub cache: &'a Cache,
+    pub visual: V,
+}
+
+impl<'a, V: Visual, Message> Program<Message> for Cached<'a, V> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> Vec<Geometry> {
+        vec![self
+            .cache
+            .draw(bounds.size(), |frame| self.visual.draw(frame))]
+    }
+}
@@ -0,0 +1,673 @@
+use std::fmt;
+
+use iced::widget::canvas::{path, Fill, Frame};
+use iced::{Color, Point, Rectangle, Size};
+
+use crate::gain::GainMapping;
+use crate::sample_ring::SampleRing;
+use crate::visual::Visual;
+use crate::waveform_style::WaveformStyle;
+
+/// Lowest frequency in Hz the trigger is guaranteed to find an edge for, on top of the holdoff.
+const MIN_TRIGGER_FREQUENCY: f32 = 10.;
+
+/// Longer windows are reduced to this many envelope columns before anything else sees them, a
+/// few seconds of audio would otherwise be smoothed and drawn sample by sample every frame.
+const MAX_WINDOW_COLUMNS: usize = 4096;
+
+const BAR_GAP: f32 = 2.;
+const PEAK_HEIGHT: f32 = 3.;
+
+/// Color of the second channel when both are shown, the first uses the [`WaveformStyle`].
+const RIGHT_COLOR: Color = Color::from_rgb(0.8, 0., 0.8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChannelMode {
+    #[default]
+    Mono,
+    Left,
+    Right,
+    Stacked,
+    Overlaid,
+}
+
+impl ChannelMode {
+    pub const ALL: [ChannelMode; 5] = [
+        ChannelMode::Mono,
+        ChannelMode::Left,
+        ChannelMode::Right,
+        ChannelMode::Stacked,
+        ChannelMode::Overlaid,
+    ];
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|x| *x == self).unwrap_or(0);
+
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for ChannelMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ChannelMode::Mono => "Mono Downmix",
+            ChannelMode::Left => "Left Only",
+            ChannelMode::Right => "Right Only",
+            ChannelMode::Stacked => "Stacked L/R",
+            ChannelMode::Overlaid => "Overlaid L/R",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TriggerEdge {
+    /// Free running, always shows the latest samples.
+    Off,
+    #[default]
+    Rising,
+    Falling,
+}
+
+impl TriggerEdge {
+    pub const ALL: [TriggerEdge; 3] = [TriggerEdge::Off, TriggerEdge::Rising, TriggerEdge::Falling];
+}
+
+impl fmt::Display for TriggerEdge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TriggerEdge::Off => "Free Running",
+            TriggerEdge::Rising => "Rising Edge",
+            TriggerEdge::Falling => "Falling Edge",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriggerConfig {
+    pub edge: TriggerEdge,
+    pub level: f32,
+    /// How far the signal has to move back past `level` before the trigger re-arms, so noise
+    /// around the level doesn't fire it over and over.
+    pub hysteresis: f32,
+    /// Minimum time in milliseconds between two triggers.
+    pub holdoff_ms: f32,
+    /// Time shown across the canvas in milliseconds.
+    pub window_ms: f32,
+}
+
+impl Default for TriggerConfig {
+    fn default() -> Self {
+        TriggerConfig {
+            edge: TriggerEdge::default(),
+            level: 0.,
+            hysteresis: 0.05,
+            holdoff_ms: 0.,
+            window_ms: 40.,
+        }
+    }
+}
+
+/// Picks the window of samples the waveform shows, lined up on a trigger point so periodic
+/// signals stand still.
+#[derive(Debug, Default)]
+pub struct Oscilloscope {
+    pub config: TriggerConfig,
+    /// Absolute frame index in the ring of the last trigger, used for the holdoff.
+    last_trigger: Option<usize>,
+    samples: Vec<f32>,
+    /// Interleaved frames currently on screen.
+    window: Vec<f32>,
+}
+
+impl Oscilloscope {
+    pub fn new(config: TriggerConfig) -> Self {
+        Oscilloscope {
+            config,
+            ..Oscilloscope::default()
+        }
+    }
+
+    pub fn window(&self) -> &[f32] {
+        &self.window
+    }
+
+    pub fn clear(&mut self) {
+        self.last_trigger = None;
+        self.window.clear();
+    }
+
+    pub fn update(
+        &mut self,
+        ring: &SampleRing,
+        channels: u16,
+        sample_rate: u32,
+        envelope: Envelope,
+    ) {
+        let channels = channels.max(1) as usize;
+        let TriggerConfig {
+            edge,
+            level,
+            hysteresis,
+            holdoff_ms,
+            window_ms,
+        } = self.config;
+
+        // Keep half the ring for looking back for a trigger.
+        let max_frames = ring.capacity() / channels / 2;
+        let window = ((window_ms / 1000. * sample_rate as f32) as usize).clamp(2, max_frames);
+        let holdoff = (holdoff_ms / 1000. * sample_rate as f32) as usize;
+
+        // Looking back one slow period past the holdoff is enough to find the next edge.
+        let search = match edge {
+            TriggerEdge::Off => 0,
+            _ => window.min(holdoff + (sample_rate as f32 / MIN_TRIGGER_FREQUENCY) as usize),
+        };
+        let end = ring.snapshot(&mut self.samples, (window + search) * channels);
+        // A snapshot the producer cut short can start in the middle of a frame.
+        self.samples.drain(..self.samples.len() % channels);
+
+        let frames = self.samples.len() / channels;
+        let first_frame = (end / channels).saturating_sub(frames);
+        // Only the search area needs a trigger, plus the frame after it.
+        let searched = (frames.saturating_sub(window) + 1).min(frames);
+        let mono = downmix(&self.samples[..searched * channels], channels);
+
+        let mut start = None;
+        let mut armed = false;
+
+        // Walk forward through the search area accepting triggers that respect the holdoff; the
+        // last one whose window still fits is shown.
+        for (i, pair) in mono
+            .windows(2)
+            .enumerate()
+            .take(frames.saturating_sub(window))
+        {
+            let (previous, sample) = (pair[0], pair[1]);
+            let frame = first_frame + i + 1;
+
+            armed |= match edge {
+                TriggerEdge::Off => false,
+                TriggerEdge::Rising => previous < level - hysteresis,
+                TriggerEdge::Falling => previous > level + hysteresis,
+            };
+
+            let crossed = match edge {
+                TriggerEdge::Off => false,
+                TriggerEdge::Rising => sample >= level,
+                TriggerEdge::Falling => sample <= level,
+            };
+
+            if armed && crossed {
+                armed = false;
+
+                if self
+                    .last_trigger
+                    .is_none_or(|x| frame >= x + holdoff.max(1))
+                {
+                    self.last_trigger = Some(frame);
+                    start = Some(i + 1);
+                }
+            }
+        }
+
+        // Without a new one, stay on the previous trigger while its window is still around, e.g.
+        // during a long holdoff, below the search range or while paused. Failing that, show the
+        // latest samples like a scope in auto mode.
+        let start = start
+            .or_else(|| {
+                self.last_trigger
+                    .and_then(|x| x.checked_sub(first_frame))
+                    .filter(|x| x + window <= frames)
+            })
+            .unwrap_or(frames.saturating_sub(window));
+
+        let shown = &self.samples[start * channels..(start + window.min(frames)) * channels];
+
+        self.window.clear();
+
+        if shown.len() / channels > MAX_WINDOW_COLUMNS * 2 {
+            reduce(
+                shown,
+                channels,
+                MAX_WINDOW_COLUMNS,
+                envelope,
+                &mut self.window,
+            );
+        } else {
+            self.window.extend_from_slice(shown);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Envelope {
+    /// Lowest and highest sample in each pixel column.
+    #[default]
+    MinMax,
+    /// Root mean square of each pixel column, mirrored around zero.
+    Rms,
+}
+
+impl Envelope {
+    pub const ALL: [Envelope; 2] = [Envelope::MinMax, Envelope::Rms];
+}
+
+impl fmt::Display for Envelope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Envelope::MinMax => "Min/Max",
+            Envelope::Rms => "RMS",
+        })
+    }
+}
+
+/// Pulls a single channel out of an interleaved buffer. Missing channels fall back to the last
+/// one, so a mono source shows the same data for left and right.
+pub fn channel(data: &[f32], channels: usize, index: usize) -> Vec<f32> {
+    let index = index.min(channels - 1);
+
+    data.chunks_exact(channels).map(|x| x[index]).collect()
+}
+
+/// Averages every channel of an interleaved buffer down to one.
+pub fn downmix(data: &[f32], channels: usize) -> Vec<f32> {
+    data.chunks_exact(channels)
+        .map(|x| x.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+pub struct Waveform<'a> {
+    /// Interleaved frames to draw across the full width.
+    pub data: &'a [f32],
+    pub channels: u16,
+    pub channel_mode: ChannelMode,
+    /// How samples are reduced once there are more of them than pixel columns.
+    pub envelope: Envelope,
+    pub style: WaveformStyle,
+    pub gain: GainMapping,
+    /// Held absolute level of every sample in `data`, drawn over the bars. Empty when peak hold
+    /// is off.
+    pub peaks: &'a [f32],
+}
+
+impl<'a> Visual for Waveform<'a> {
+    fn draw(&self, frame: &mut Frame) {
+        let bounds = Rectangle::with_size(frame.size());
+
+        let channels = self.channels.max(1) as usize;
+        let data = self.data;
+
+        if data.len() < channels {
+            return;
+        }
+
+        let full = Rectangle::with_size(bounds.size());
+        let top = Rectangle {
+            height: bounds.height / 2.,
+            ..full
+        };
+        let bottom = Rectangle {
+            y: top.height,
+            ..top
+        };
+
+        let style = self.style;
+        let right_style = style.with_color(RIGHT_COLOR);
+
+        // Each lane shows one channel, or the downmix of all of them.
+        let lanes = match self.channel_mode {
+            ChannelMode::Mono => vec![(None, full, style)],
+            ChannelMode::Left => vec![(Some(0), full, style)],
+            ChannelMode::Right => vec![(Some(1), full, style)],
+            ChannelMode::Stacked => vec![(Some(0), top, style), (Some(1), bottom, right_style)],
+            ChannelMode::Overlaid => vec![(Some(0), full, style), (Some(1), full, right_style)],
+        };
+
+        let lane = |data: &[f32], index: Option<usize>| {
+            let values = match index {
+                Some(index) => channel(data, channels, index),
+                None => downmix(data, channels),
+            };
+
+            values
+                .into_iter()
+                .map(|v| self.gain.map(v))
+                .collect::<Vec<_>>()
+        };
+
+        for (index, band, style) in lanes {
+            let peaks = if self.peaks.len() == data.len() {
+                lane(self.peaks, index)
+            } else {
+                Vec::new()
+            };
+
+            draw_lane(
+                frame,
+                &lane(data, index),
+                &peaks,
+                band,
+                &style,
+                self.envelope,
+            );
+        }
+    }
+}
+
+fn draw_lane(
+    frame: &mut Frame,
+    data: &[f32],
+    peaks: &[f32],
+    band: Rectangle,
+    style: &WaveformStyle,
+    envelope: Envelope,
+) {
+    if data.is_empty() {
+        return;
+    }
+
+    // Positive samples go up.
+    let y = |v: f32| band.y + band.height / 2. - v * band.height / 2.;
+
+    if style.bars {
+        let spacing = style.width.max(1.) + BAR_GAP;
+        let count = ((band.width / spacing) as usize).clamp(1, data.len());
+        let spacing = band.width / count as f32;
+        let width = (spacing - BAR_GAP).max(1.);
+
+        for (i, (low, high)) in decimate(data, count, Envelope::MinMax)
+            .into_iter()
+            .enumerate()
+        {
+            let (low, high) = if style.mirror {
+                let peak = low.abs().max(high.abs());
+                (-peak, peak)
+            } else {
+                (low, high)
+            };
+
+            frame.fill_rectangle(
+                Point::new(band.x + i as f32 * spacing, y(high)),
+                Size::new(width, (y(low) - y(high)).max(1.)),
+                Fill {
+                    style: style.paint(band),
+                    ..Fill::default()
+                },
+            );
+        }
+
+        if !peaks.is_empty() {
+            for (i, (_, peak)) in decimate(peaks, count, Envelope::MinMax)
+                .into_iter()
+                .enumerate()
+            {
+                let x = band.x + i as f32 * spacing;
+
+                for top in [y(peak) - PEAK_HEIGHT, y(-peak)] {
+                    frame.fill_rectangle(
+                        Point::new(x, top),
+                        Size::new(width, PEAK_HEIGHT),
+                        Fill {
+                            style: style.paint(band),
+                            ..Fill::default()
+                        },
+                    );
+                }
+            }
+        }
+
+        return;
+    }
+
+    let columns = band.width.max(1.) as usize;
+
+    // Once several samples share a pixel a polyline only aliases, so draw their envelope.
+    let decimated = data.len() > columns * 2;
+    let mut values = if decimated {
+        decimate(data, columns, envelope)
+    } else {
+        data.iter().map(|v| (*v, *v)).collect()
+    };
+
+    if style.mirror {
+        for (low, high) in values.iter_mut() {
+            let peak = low.abs().max(high.abs());
+            (*low, *high) = (-peak, peak);
+        }
+    }
+
+    let x = |i: usize| band.x + i as f32 * band.width / values.len() as f32;
+
+    // A line with no current point starts a new subpath there.
+    let trace_top = |builder: &mut path::Builder| {
+        for (i, (_, high)) in values.iter().enumerate() {
+            builder.line_to(Point::new(x(i), y(*high)));
+        }
+    };
+
+    let mut path_builder = path::Builder::new();
+    trace_top(&mut path_builder);
+
+    let single_line = !decimated && !style.mirror;
+
+    if single_line {
+        if style.fill {
+            let mut area = path::Builder::new();
+            area.move_to(Point::new(x(0), y(0.)));
+            trace_top(&mut area);
+            area.line_to(Point::new(x(values.len() - 1), y(0.)));
+            area.close();
+
+            frame.fill(
+                &area.build(),
+                Fill {
+                    style: style.paint(band),
+                    ..Fill::default()
+                },
+            );
+        }
+    } else {
+        for (i, (low, _)) in values.iter().enumerate().rev() {
+            path_builder.line_to(Point::new(x(i), y(*low)));
+        }
+
+        path_builder.close();
+    }
+
+    let path = path_builder.build();
+
+    // A min/max envelope is always filled, the outline keeps quiet passages visible where it
+    // is thinner than a pixel.
+    if !single_line && (style.fill || decimated) {
+        frame.fill(
+            &path,
+            Fill {
+                style: style.paint(band),
+                ..Fill::default()
+            },
+        );
+    }
+
+    for stroke in style.glow_strokes() {
+        frame.stroke(&path, stroke);
+    }
+
+    frame.stroke(&path, style.stroke(band));
+}
+
+/// Reduces interleaved frames to two frames per column, the low and the high of each channel's
+/// envelope. Decimating the result again gives the same envelope as decimating `data`.
+fn reduce(data: &[f32], channels: usize, columns: usize, envelope: Envelope, out: &mut Vec<f32>) {
+    let reduced = (0..channels)
+        .map(|index| decimate(&channel(data, channels, index), columns, envelope))
+        .collect::<Vec<_>>();
+
+    for column in 0..columns {
+        out.extend(reduced.iter().map(|x| x[column].0));
+        out.extend(reduced.iter().map(|x| x[column].1));
+    }
+}
+
+/// Reduces `data` to a `(low, high)` pair per column.
+fn decimate(data: &[f32], columns: usize, envelope: Envelope) -> Vec<(f32, f32)> {
+    (0..columns)
+        .map(|column| {
+            let chunk = &data[column * data.len() / columns..(column + 1) * data.len() / columns];
+
+            match envelope {
+                Envelope::MinMax => chunk.iter().fold((f32::MAX, f32::MIN), |(low, high), v| {
+                    (low.min(*v), high.max(*v))
+                }),
+                Envelope::Rms => {
+                    let rms =
+                        (chunk.iter().map(|v| v * v).sum::<f32>() / chunk.len() as f32).sqrt();
+
+                    (-rms, rms)
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use super::*;
+
+    /// A sine of `period` frames, rising through zero at frame `offset`.
+    fn sine(frames: std::ops::Range<usize>, period: usize, offset: usize) -> Vec<f32> {
+        frames
+            .map(|i| (TAU * (i as f32 - offset as f32) / period as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn triggers_on_a_rising_edge() {
+        let ring = SampleRing::new(1 << 12);
+        let mut scope = Oscilloscope::new(TriggerConfig {
+            window_ms: 50.,
+            ..TriggerConfig::default()
+        });
+
+        // A 20 Hz sine at 1 kHz, rising through zero at frame 30 and every 50 frames after.
+        ring.push_slice(&sine(0..200, 50, 30));
+        scope.update(&ring, 1, 1000, Envelope::MinMax);
+
+        let window = scope.window();
+
+        assert_eq!(window.len(), 50);
+        assert!(window[0].abs() < 1e-3);
+        assert!(window[1] > 0.);
+    }
+
+    #[test]
+    fn falling_edge_and_free_running() {
+        let ring = SampleRing::new(1 << 12);
+        let mut scope = Oscilloscope::new(TriggerConfig {
+            edge: TriggerEdge::Falling,
+            window_ms: 50.,
+            ..TriggerConfig::default()
+        });
+
+        ring.push_slice(&sine(0..200, 50, 30));
+        scope.update(&ring, 1, 1000, Envelope::MinMax);
+
+        assert!(scope.window()[0].abs() < 1e-3);
+        assert!(scope.window()[1] < 0.);
+
+        // Free running always shows the newest samples.
+        scope.config.edge = TriggerEdge::Off;
+        scope.update(&ring, 1, 1000, Envelope::MinMax);
+
+        assert_eq!(scope.window(), &sine(150..200, 50, 30)[..]);
+    }
+
+    #[test]
+    fn triggers_on_the_downmix() {
+        let ring = SampleRing::new(1 << 12);
+        let mut scope = Oscilloscope::new(TriggerConfig {
+            window_ms: 50.,
+            ..TriggerConfig::default()
+        });
+
+        let left = sine(0..200, 50, 30);
+        ring.push_slice(&left.iter().flat_map(|x| [*x, *x * 0.5]).collect::<Vec<_>>());
+        scope.update(&ring, 2, 1000, Envelope::MinMax);
+
+        assert_eq!(scope.window().len(), 100);
+        assert!(scope.window()[0].abs() < 1e-3);
+    }
+
+    #[test]
+    fn decimates_to_min_max_and_rms() {
+        let data = [0., 1., -1., 0.5, 0.25, -0.5];
+
+        assert_eq!(
+            decimate(&data, 2, Envelope::MinMax),
+            [(-1., 1.), (-0.5, 0.5)]
+        );
+
+        let rms = decimate(&[1., -1., 0.5, -0.5], 2, Envelope::Rms);
+        assert_eq!(rms, [(-1., 1.), (-0.5, 0.5)]);
+    }
+
+    #[test]
+    fn decimates_uneven_lengths() {
+        let columns = decimate(&[1., 2., 3., 4., 5.], 2, Envelope::MinMax);
+
+        assert_eq!(columns, [(1., 2.), (3., 5.)]);
+    }
+
+    #[test]
+    fn splits_and_downmixes_channels() {
+        let data = [1., 3., 2., 4.];
+
+        assert_eq!(channel(&data, 2, 0), [1., 2.]);
+        assert_eq!(channel(&data, 2, 1), [3., 4.]);
+        // A mono source shows the same data for the right channel.
+        assert_eq!(channel(&[1., 2.], 1, 1), [1., 2.]);
+        assert_eq!(downmix(&data, 2), [2., 3.]);
+    }
+
+    #[test]
+    fn reduces_long_windows_to_their_envelope() {
+        let ring = SampleRing::new(1 << 20);
+        let mut scope = Oscilloscope::new(TriggerConfig {
+            edge: TriggerEdge::Off,
+            window_ms: 5000.,
+            ..TriggerConfig::default()
+        });
+
+        ring.push_slice(&sine(0..48000 * 5, 40, 0));
+        scope.update(&ring, 1, 48000, Envelope::MinMax);
+
+        assert_eq!(scope.window().len(), MAX_WINDOW_COLUMNS * 2);
+        assert!(scope
+            .window()
+            .chunks(2)
+            .all(|x| x[0] < -0.99 && x[1] > 0.99));
+    }
+
+    #[test]
+    fn holds_previous_trigger_during_holdoff() {
+        let ring = SampleRing::new(1 << 12);
+        let mut scope = Oscilloscope::new(TriggerConfig {
+            holdoff_ms: 1000.,
+            window_ms: 50.,
+            ..TriggerConfig::default()
+        });
+
+        ring.push_slice(&sine(0..100, 100, 25));
+        scope.update(&ring, 1, 1000, Envelope::MinMax);
+        let triggered = scope.window().to_vec();
+
+        assert!(triggered[0].abs() < 1e-3);
+
+        // The same edge is still in view but too soon to fire again.
+        ring.push_slice(&sine(100..110, 100, 25));
+        scope.update(&ring, 1, 1000, Envelope::MinMax);
+
+        assert_eq!(scope.window(), triggered);
+    }
+}
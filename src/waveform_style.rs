@@ -0,0 +1,208 @@
+use std::fmt;
+
+use iced::widget::canvas::{self, stroke::Stroke, Gradient, LineCap, LineJoin};
+use iced::{Color, Point, Rectangle};
+use serde::{Deserialize, Serialize};
+
+use crate::config::hex_color;
+
+/// Extra stroke width added by each glow layer.
+const GLOW_SPREAD: f32 = 4.;
+const GLOW_ALPHA: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Cap {
+    #[default]
+    Butt,
+    Square,
+    Round,
+}
+
+impl Cap {
+    pub const ALL: [Cap; 3] = [Cap::Butt, Cap::Square, Cap::Round];
+}
+
+impl From<Cap> for LineCap {
+    fn from(cap: Cap) -> Self {
+        match cap {
+            Cap::Butt => LineCap::Butt,
+            Cap::Square => LineCap::Square,
+            Cap::Round => LineCap::Round,
+        }
+    }
+}
+
+impl fmt::Display for Cap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Cap::Butt => "Butt Cap",
+            Cap::Square => "Square Cap",
+            Cap::Round => "Round Cap",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Join {
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+impl Join {
+    pub const ALL: [Join; 3] = [Join::Miter, Join::Round, Join::Bevel];
+}
+
+impl From<Join> for LineJoin {
+    fn from(join: Join) -> Self {
+        match join {
+            Join::Miter => LineJoin::Miter,
+            Join::Round => LineJoin::Round,
+            Join::Bevel => LineJoin::Bevel,
+        }
+    }
+}
+
+impl fmt::Display for Join {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Join::Miter => "Miter Join",
+            Join::Round => "Round Join",
+            Join::Bevel => "Bevel Join",
+        })
+    }
+}
+
+/// How the waveform of the first channel is painted.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaveformStyle {
+    #[serde(with = "hex_color")]
+    pub color: Color,
+    /// End color of a left to right gradient starting at `color`.
+    #[serde(with = "hex_color::option", skip_serializing_if = "Option::is_none")]
+    pub gradient: Option<Color>,
+    pub width: f32,
+    pub cap: Cap,
+    pub join: Join,
+    /// Fills the area between the curve and the center line.
+    pub fill: bool,
+    /// Draws the magnitude of the signal above and below the center line.
+    pub mirror: bool,
+    /// Number of wider, faint strokes drawn underneath to fake a glow.
+    pub glow: u8,
+    /// Draws peak bars instead of a line.
+    pub bars: bool,
+}
+
+impl Default for WaveformStyle {
+    fn default() -> Self {
+        WaveformStyle {
+            color: Color::BLACK,
+            gradient: None,
+            width: 2.,
+            cap: Cap::default(),
+            join: Join::default(),
+            fill: false,
+            mirror: false,
+            glow: 0,
+            bars: false,
+        }
+    }
+}
+
+impl WaveformStyle {
+    /// Solid color or gradient spanning `band`.
+    pub fn paint(&self, band: Rectangle) -> canvas::Style {
+        let Some(end) = self.gradient else {
+            return canvas::Style::Solid(self.color);
+        };
+
+        let start_point = Point::new(band.x, band.y);
+        let end_point = Point::new(band.x + band.width, band.y);
+
+        match Gradient::linear((start_point, end_point))
+            .add_stop(0., self.color)
+            .add_stop(1., end)
+            .build()
+        {
+            Ok(gradient) => canvas::Style::Gradient(gradient),
+            Err(_) => canvas::Style::Solid(self.color),
+        }
+    }
+
+    pub fn stroke(&self, band: Rectangle) -> Stroke<'static> {
+        Stroke {
+            style: self.paint(band),
+            width: self.width,
+            line_cap: self.cap.into(),
+            line_join: self.join.into(),
+            ..Stroke::default()
+        }
+    }
+
+    /// Faint strokes to draw before the main one, widest first.
+    pub fn glow_strokes(&self) -> impl Iterator<Item = Stroke<'static>> + '_ {
+        (1..=self.glow).rev().map(move |layer| Stroke {
+            style: canvas::Style::Solid(Color {
+                a: GLOW_ALPHA,
+                ..self.color
+            }),
+            width: self.width + layer as f32 * GLOW_SPREAD,
+            line_cap: self.cap.into(),
+            line_join: self.join.into(),
+            ..Stroke::default()
+        })
+    }
+
+    /// Same shape settings with a plain color, used for the second channel.
+    pub fn with_color(self, color: Color) -> Self {
+        WaveformStyle {
+            color,
+            gradient: None,
+            ..self
+        }
+    }
+}
+
+/// Formats a color as `#rrggbb`.
+pub fn to_hex(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Parses `#rrggbb` or `rrggbb`.
+pub fn parse_hex(hex: &str) -> Option<Color> {
+    let hex = hex.trim().trim_start_matches('#');
+
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    Some(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trips() {
+        let color = Color::from_rgb8(0x12, 0xab, 0xff);
+
+        assert_eq!(to_hex(color), "#12abff");
+        assert_eq!(parse_hex("#12abff"), Some(color));
+        assert_eq!(parse_hex(" 12ABFF "), Some(color));
+    }
+
+    #[test]
+    fn rejects_invalid_hex() {
+        for invalid in ["", "#12ab", "#12abff00", "#12abgg", "#12abé"] {
+            assert_eq!(parse_hex(invalid), None, "{invalid}");
+        }
+    }
+}